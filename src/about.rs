@@ -0,0 +1,49 @@
+// src/about.rs
+
+use crate::{
+  gem::{GemDoc, StatusText},
+  bookmark,
+  history,
+};
+use percent_encoding::{percent_decode_str};
+use std::{
+  path::{Path},
+};
+use url::{Url};
+
+// module: about
+//
+// Pages made here rather than fetched,
+// 'about:bookmarks' and friends.
+
+
+pub const BOOKMARKS: &str = "about:bookmarks";
+
+
+pub fn get_doc(url: &Url, dir: &Path) -> Result<GemDoc, String> {
+
+  let text = match (url.path(), url.query()) {
+    ("bookmarks", _) =>
+      bookmark::page(dir),
+
+    ("history", _) =>
+      history::page(dir, None),
+
+    // ask for a filter, then show what matches it
+    ("filter-history", None) => {
+      let status = StatusText::parse(
+        "10 filter history by url, title or day:");
+      return Ok(GemDoc::from_response(url, status, vec![]))
+    }
+    ("filter-history", Some(query)) => {
+      let filter = percent_decode_str(query).decode_utf8_lossy();
+      history::page(dir, Some(&filter))
+    }
+
+    _ =>
+      return Err(format!("no page called {}", url)),
+  };
+
+  let status = StatusText::parse("20 text/gemini");
+  Ok(GemDoc::from_response(url, status, text.into_bytes()))
+}
@@ -1,22 +1,31 @@
 // src/app.rs
 
 use crate::{
-  usr::{User},
+  usr::{User, UserHandler},
   page::{Page},
   msg::{Focus, ViewMsg},
+  gem::{GemText, GemTag},
   text::{Doc},
-  tab::Tab,
+  tab::{Tab, Session},
+  tofu::{self, KNOWN_HOSTS},
+  msglog::{MsgLog},
+  session,
 };
 use crossterm::{
   QueueableCommand, cursor,
-  terminal::{Clear, ClearType},
+  terminal::{
+    self, Clear, ClearType, 
+    EnterAlternateScreen, LeaveAlternateScreen, DisableLineWrap,
+  },
   event::{
     Event, KeyEvent, KeyEventKind, KeyCode, KeyModifiers,
   }
 };
 use std::{
   fs,
-  io::{self, Write}
+  process::{Command, Stdio},
+  thread,
+  io::{self, stdout, Write}
 };
 
 // coordinator, the brain. 
@@ -27,6 +36,11 @@ use std::{
 pub struct App {
   pub hdr:      Doc,
   pub tabs:     Vec<Tab>,
+  // the tab overview
+  pub tab_doc:  Doc,
+  // everything the app had to say
+  pub msgs:     MsgLog,
+  pub msg_doc:  Doc,
   pub hdr_page: Page,
   pub tab_page: Page,
   pub idx:      usize,
@@ -38,11 +52,17 @@ pub struct App {
 } 
 impl App {
 
-  pub fn init(path: &str, w: u16, h: u16) -> Self {
+  // url, if given, replaces init_url
+  pub fn init(path: &str, url: Option<&str>, w: u16, h: u16) -> Self {
 
-    let usr = Self::load_config(path);
+    // no config means defaults, and a note of why
+    let (usr, err) = match Self::load_config(path) {
+      Ok(usr) => (usr, None),
+      Err(e)  => (User::default().dir(path), Some(e)),
+    };
     let (hdr_page, tab_page) = 
       usr.get_layout(w, h);
+    let init_url = url.unwrap_or(&usr.init_url);
 
     let mut app = Self {
       usr_path: path.into(),
@@ -50,14 +70,35 @@ impl App {
       focus:    Focus::Tab,
       idx:      0,  
       hdr:      Doc::default(),
+      tab_doc:  Doc::default(),
+      msgs:     MsgLog::default(),
+      msg_doc:  Doc::default(),
       clr_scr:  false,
       tabs:     vec![
-        Tab::init(&tab_page, &usr.init_url, &usr)],
+        Tab::init(&tab_page, init_url, &usr)],
       hdr_page, 
       tab_page,
       usr,
     };
 
+    // a url to open means no one's asking for the old tabs.
+    // the question gets a tab of its own, 
+    // so nothing the first fetch brings can replace it
+    if url.is_none() 
+      && let Some((_, tabs)) = session::load(&app.usr.dir) 
+    {
+      let text = format!("restore {} tabs from last time?", tabs.len());
+      let mut tab = Tab::empty(&app.tab_page, "restore");
+      tab.confirm(&app.usr, &text, ViewMsg::Restore);
+      app.tabs.insert(0, tab);
+    }
+
+    if let Some(e) = err {
+      let last = app.tabs.len() - 1;
+      app.tabs[last].notify(&app.usr, &format!("{}: {}", path, e));
+      app.collect_msgs();
+    }
+
     app.update_hdr_text();
     app
   }
@@ -73,7 +114,15 @@ impl App {
     }
 
     self.hdr.view(&self.hdr_page, writer)?;
-    self.tabs[self.idx].view(writer)?;
+
+    match self.focus {
+      Focus::TabView => 
+        self.tab_doc.view(&self.tab_page, writer)?,
+      Focus::MsgView => 
+        self.msg_doc.view(&self.tab_page, writer)?,
+      _ => 
+        self.tabs[self.idx].view(writer)?,
+    }
 
     writer
       .queue(cursor::Show)?
@@ -93,6 +142,8 @@ impl App {
           kind: KeyEventKind::Press, ..
         }
       ) => {
+        // nowhere to show a failure, the next launch just won't offer
+        let _ = self.save_session();
         self.quit = true;
         true
       }
@@ -113,6 +164,10 @@ impl App {
         let response = match &self.focus {
           Focus::Global => 
             self.update_global(&kc),
+          Focus::TabView => 
+            self.update_tab_view(&kc),
+          Focus::MsgView => 
+            self.update_msg_view(&kc),
           Focus::Tab => 
             self.tabs[self.idx]
               .update(&self.usr, &kc),
@@ -120,6 +175,7 @@ impl App {
 
         if let Some(msg) = response { 
           self.update_from_view_msg(msg);
+          self.collect_msgs();
           self.update_hdr_text();
           true
         } else {
@@ -133,6 +189,46 @@ impl App {
   }
 
 
+  // collect finished requests, 
+  // returns true if the screen needs redrawing
+  pub fn poll(&mut self) -> bool {
+
+    let mut redraw = false;
+    let mut changed = false;
+
+    for (i, t) in self.tabs.iter_mut().enumerate() {
+      if t.poll(&self.usr) {
+        changed = true;
+        redraw |= i == self.idx;
+      }
+    }
+
+    // the overview shows every tab's status
+    if changed && matches!(self.focus, Focus::TabView) {
+      self.refresh_tab_view();
+      redraw = true;
+    }
+
+    if changed && self.collect_msgs() 
+      && matches!(self.focus, Focus::MsgView) 
+    {
+      self.refresh_msg_view();
+      redraw = true;
+    }
+
+    if redraw {
+      self.update_hdr_text();
+      self.clr_scr = true;
+
+    // progress only changes the header
+    } else if self.tabs[self.idx].progressed() {
+      self.update_hdr_text();
+      redraw = true;
+    }
+    redraw
+  }
+
+
   fn update_from_view_msg(&mut self, msg: ViewMsg) {
 
     match msg {
@@ -141,19 +237,24 @@ impl App {
         self.focus = Focus::Global;
       }
 
+      ViewMsg::Redraw => {
+        self.clr_scr = true;
+      }
+
       ViewMsg::ReloadUser => {
-        self.update_usr(
-          Self::load_config(&self.usr_path));
+        self.reload_usr(&self.usr_path.clone());
         self.clr_scr = true;
       }
 
       ViewMsg::NewUser(s) => {
-        self.usr_path = s;
-        self.update_usr(
-          Self::load_config(&self.usr_path));
+        self.reload_usr(&s);
         self.clr_scr = true;
       }
 
+      ViewMsg::Msg(text) => {
+        self.msgs.push(&text);
+      }
+
       ViewMsg::Go(url) => {
         let tab = Tab::init(
           &self.tab_page, &url, &self.usr);
@@ -163,12 +264,129 @@ impl App {
         self.clr_scr = true;
       }
 
+      ViewMsg::Follow(url) => {
+        self.tabs[self.idx].make_request(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::FollowSecret(url) => {
+        self.tabs[self.idx].make_secret_request(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Redirect(url, hist, permanent) => {
+        self.tabs[self.idx]
+          .follow_redirect(&self.usr, &url, hist, permanent);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::TrustOnce(url, host, cert) => {
+        tofu::trust_once(&host, &cert);
+        self.tabs[self.idx].make_request(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::TrustAlways(url, host, cert) => {
+        let path = self.usr.dir.join(KNOWN_HOSTS);
+        // still let this run through if we can't remember
+        if let Err(e) = tofu::trust_always(&path, &host, &cert) {
+          self.msgs.push(&format!(
+            "could not remember the certificate for {}: {}", host, e));
+          tofu::trust_once(&host, &cert);
+        }
+        self.tabs[self.idx].make_request(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::UseIdentity(url, name) => {
+        self.tabs[self.idx]
+          .use_identity(&self.usr, &url, &name);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Save(name) => {
+        self.tabs[self.idx].save(&self.usr, &name);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Open(handler, url, file) => {
+        if let Err(e) = Self::run(&handler, &url, &file) {
+          self.tabs[self.idx].notify(
+            &self.usr, 
+            &format!("{}: {}", handler.cmd, e));
+        }
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Titan(url) => {
+        self.tabs[self.idx].titan(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::PickFile(url) => {
+        self.tabs[self.idx].pick_file(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Compose(url) => {
+        self.tabs[self.idx].compose(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::AttachFile(url, file) => {
+        self.tabs[self.idx].attach_file(&self.usr, &url, &file);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Attach(url, mime, data) => {
+        self.tabs[self.idx].attach(&self.usr, &url, &mime, data);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Upload(url, mime, data, token) => {
+        self.tabs[self.idx]
+          .upload(&self.usr, &url, &mime, data, &token);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::AddBookmark(url, title, folder) => {
+        self.tabs[self.idx]
+          .add_bookmark(&self.usr, &url, &title, &folder);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Unbookmark(url) => {
+        self.tabs[self.idx].remove_bookmark(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Rename(url) => {
+        self.tabs[self.idx].rename_bookmark(&self.usr, &url);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Retitle(url, title) => {
+        self.tabs[self.idx]
+          .retitle_bookmark(&self.usr, &url, &title);
+        self.clr_scr = true;
+      }
+
+      ViewMsg::Restore => {
+        self.restore_session();
+        self.clr_scr = true;
+      }
+
       ViewMsg::DeleteMe => {
         if self.tabs.len() > 1 {
+          self.tabs[self.idx].cancel();
           self.tabs.remove(self.idx);
           self.idx = self.tabs.len() - 1;
-          self.clr_scr = true;
+        } else {
+          // there's always one tab
+          self.tabs[self.idx].notify(
+            &self.usr, "the last tab stays open");
         }
+        self.clr_scr = true;
       }
 
       ViewMsg::CycleLeft => {
@@ -194,6 +412,48 @@ impl App {
   }
 
 
+  // run an outside program, giving it the terminal
+  // for as long as it runs if it's in the foreground
+  fn run(handler: &UserHandler, url: &str, file: &str) 
+    -> io::Result<()> 
+  {
+    let args = handler.args(url, file);
+    let Some((prog, args)) = args.split_first() 
+      else {return Err(io::Error::other("empty command"))};
+
+    let mut cmd = Command::new(prog);
+    cmd.args(args);
+
+    if !handler.foreground {
+      let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+      // reap it whenever it's done
+      thread::spawn(move || child.wait());
+      return Ok(())
+    }
+
+    let mut stdout = stdout();
+    terminal::disable_raw_mode()?;
+    stdout.queue(LeaveAlternateScreen)?.flush()?;
+
+    let status = cmd.status();
+
+    terminal::enable_raw_mode()?;
+    stdout
+      .queue(EnterAlternateScreen)?
+      .queue(DisableLineWrap)?
+      .flush()?;
+
+    match status? {
+      s if s.success() => Ok(()),
+      s => Err(io::Error::other(s.to_string())),
+    }
+  }
+
+
   fn resize(&mut self, w: u16, h: u16) {
 
     let (hdr_page, tab_page) = 
@@ -205,26 +465,118 @@ impl App {
     for t in self.tabs.iter_mut() {
       t.resize(&self.tab_page);
     }
+    self.tab_doc.resize(&self.tab_page);
+    self.msg_doc.resize(&self.tab_page);
     self.update_hdr_text();
   }
 
 
   fn update_hdr_text(&mut self) {
 
-    let info = format!("{}/{}: {}", 
-                       self.idx + 1, 
-                       self.tabs.len(), 
-                       &self.tabs[self.idx].name);
+    let overview = match self.focus {
+      Focus::TabView => 
+        Some(format!("{} tabs", self.tabs.len())),
+      Focus::MsgView => 
+        Some(format!("{} messages", self.msgs.entries.len())),
+      _ => 
+        None,
+    };
+    if let Some(info) = overview {
+      self.hdr = self.usr
+        .get_hdr_doc(&info, &self.hdr_page);
+      return
+    }
+
+    let tab = &self.tabs[self.idx];
+    let mut info = format!("{}/{}: {}", 
+                           self.idx + 1, 
+                           self.tabs.len(), 
+                           &tab.name);
+    match tab.progress() {
+      Some(0) => 
+        info.push_str(" (loading...)"),
+      Some(n) => 
+        info.push_str(&format!(" (loading... {} bytes)", n)),
+      None => {}
+    }
+    if let Some(gdoc) = &tab.gdoc {
+      for note in gdoc.info.iter() {
+        info.push_str(&format!(" [{}]", note));
+      }
+    }
     self.hdr = self.usr
       .get_hdr_doc(&info, &self.hdr_page);
   }
 
 
-  // return default config if error
-  fn load_config(path: &str) -> User {
+  fn save_session(&self) -> Result<(), String> {
+
+    // the old session hasn't been looked at yet, keep it
+    let unanswered = self.tabs
+      .iter()
+      .any(|t| matches!(t.dlg, Some((ViewMsg::Restore, _))));
+    if unanswered {
+      return Ok(())
+    }
+
+    let kept: Vec<(usize, Session)> = self.tabs
+      .iter()
+      .enumerate()
+      .filter_map(|(i, t)| t.session().map(|s| (i, s)))
+      .collect();
+
+    // the focused tab's place among the ones kept
+    let idx = kept
+      .iter()
+      .filter(|(i, _)| *i < self.idx)
+      .count();
+
+    let tabs: Vec<Session> = kept
+      .into_iter()
+      .map(|(_, s)| s)
+      .collect();
+
+    session::save(&self.usr.dir, idx, &tabs)
+  }
+
+
+  // replace every tab with the ones saved last time
+  fn restore_session(&mut self) {
+
+    let Some((idx, sessions)) = session::load(&self.usr.dir) 
+      else {return};
+
+    for t in self.tabs.iter_mut() {
+      t.cancel();
+    }
+    self.tabs = sessions
+      .into_iter()
+      .map(|s| Tab::restore(&self.tab_page, s, &self.usr))
+      .collect();
+    self.idx = idx;
+  }
+
+
+  fn load_config(path: &str) -> Result<User, String> {
     fs::read_to_string(path)
       .map_err(|e| e.to_string())
-      .and_then(|txt| User::parse(&txt)).unwrap()
+      .and_then(|txt| User::parse(&txt))
+      .map(|usr| usr.dir(path))
+  }
+
+
+  // a broken config leaves the current one in place
+  fn reload_usr(&mut self, path: &str) {
+    match Self::load_config(path) {
+      Ok(usr) => {
+        self.usr_path = path.into();
+        self.update_usr(usr);
+      }
+      Err(e) => {
+        self.tabs[self.idx]
+          .notify(&self.usr, &format!("{}: {}", path, e));
+      }
+    }
   }
 
 
@@ -246,7 +598,15 @@ impl App {
       Some(ViewMsg::Default)
 
     } else if keycode == &self.usr.keys.tab_view {
-      self.focus = Focus::Tab;
+      self.open_tab_view();
+      Some(ViewMsg::Default)
+
+    } else if keycode == &self.usr.keys.msg_view {
+      self.focus = Focus::MsgView;
+      self.msg_doc = Doc::new(
+        self.usr.layout.gemtext_to_text(&self.msgs.page()), 
+        &self.tab_page);
+      self.clr_scr = true;
       Some(ViewMsg::Default)
 
     } else if keycode == &self.usr.keys.load_usr {
@@ -257,4 +617,199 @@ impl App {
       None
     }
   }
+
+  // one line per tab, the focused one marked
+  fn tab_view_doc(&self) -> Doc {
+
+    let gemtext: Vec<GemText> = self.tabs
+      .iter()
+      .enumerate()
+      .map(|(i, t)| {
+        let mark = if i == self.idx {"*"} else {" "};
+        let line = format!("{} {}: {} ({}) [{}, {}%]",
+                           mark, 
+                           i + 1, 
+                           t.title(), 
+                           t.name, 
+                           t.status(), 
+                           t.scroll_pct());
+        GemText::new(GemTag::ListItem, &line)
+      })
+      .collect();
+
+    Doc::new(
+      self.usr.layout.gemtext_to_text(&gemtext), 
+      &self.tab_page)
+  }
+
+
+  // start with the cursor on the focused tab
+  fn open_tab_view(&mut self) {
+
+    self.focus = Focus::TabView;
+    self.tab_doc = self.tab_view_doc();
+
+    let step = self.tab_doc.lines
+      .iter()
+      .take_while(|(i, _)| *i < self.idx)
+      .count();
+    self.tab_doc.move_down(&self.tab_page, step as u16);
+    self.clr_scr = true;
+  }
+
+
+  // rebuild, keeping the cursor where it was
+  fn refresh_tab_view(&mut self) {
+
+    let pos = self.tab_doc.pos.clone();
+    self.tab_doc = self.tab_view_doc();
+    self.tab_doc.pos = pos;
+
+    // the last line may have gone
+    let up = self.tab_doc.pos.y
+      .data_idx(&self.tab_page.text.y())
+      .saturating_sub(self.tab_doc.lines.len().saturating_sub(1));
+    self.tab_doc.move_up(&self.tab_page, up as u16);
+    self.clr_scr = true;
+  }
+
+
+  // lines the tab at i takes up in the overview
+  fn tab_view_lines(&self, i: usize) -> usize {
+    self.tab_doc.lines
+      .iter()
+      .filter(|(t, _)| *t == i)
+      .count()
+  }
+
+
+  // jump to, close or move the tab under the cursor
+  fn update_tab_view(&mut self, keycode: &KeyCode) 
+    -> Option<ViewMsg> 
+  {
+    let keys = &self.usr.keys;
+    let sel = self.tab_doc
+      .select(&self.tab_page)
+      .unwrap_or(self.idx)
+      .min(self.tabs.len() - 1);
+
+    if keycode == &keys.cancel || keycode == &keys.tab_view {
+      self.focus = Focus::Tab;
+      self.clr_scr = true;
+      Some(ViewMsg::Default)
+
+    } else if keycode == &keys.move_down {
+      self.tab_doc
+        .move_down(&self.tab_page, 1)
+        .then_some(ViewMsg::Default)
+
+    } else if keycode == &keys.move_up {
+      self.tab_doc
+        .move_up(&self.tab_page, 1)
+        .then_some(ViewMsg::Default)
+
+    } else if keycode == &keys.inspect {
+      self.idx = sel;
+      self.focus = Focus::Tab;
+      self.clr_scr = true;
+      Some(ViewMsg::Default)
+
+    } else if keycode == &keys.delete_tab {
+      if self.tabs.len() < 2 {
+        return None
+      }
+      self.tabs[sel].cancel();
+      self.tabs.remove(sel);
+      if self.idx > sel || self.idx == self.tabs.len() {
+        self.idx -= 1;
+      }
+      self.refresh_tab_view();
+      Some(ViewMsg::Default)
+
+    } else if keycode == &keys.cycle_left {
+      let other = sel.checked_sub(1)?;
+      let step = self.tab_view_lines(other);
+      self.swap_tabs(sel, other);
+      self.tab_doc.move_up(&self.tab_page, step as u16);
+      Some(ViewMsg::Default)
+
+    } else if keycode == &keys.cycle_right {
+      let other = sel + 1;
+      if other == self.tabs.len() {
+        return None
+      }
+      let step = self.tab_view_lines(other);
+      self.swap_tabs(sel, other);
+      self.tab_doc.move_down(&self.tab_page, step as u16);
+      Some(ViewMsg::Default)
+
+    } else {
+      None
+    }
+  }
+
+
+  // the focused tab stays focused wherever it goes
+  fn swap_tabs(&mut self, a: usize, b: usize) {
+    self.tabs.swap(a, b);
+    if self.idx == a {
+      self.idx = b;
+    } else if self.idx == b {
+      self.idx = a;
+    }
+    self.refresh_tab_view();
+  }
+
+  // move what the tabs had to say into the log,
+  // returns true if there was anything
+  fn collect_msgs(&mut self) -> bool {
+
+    let msgs: Vec<String> = self.tabs
+      .iter_mut()
+      .flat_map(|t| t.log.drain(..))
+      .collect();
+
+    let any = !msgs.is_empty();
+    for text in msgs {
+      self.update_from_view_msg(ViewMsg::Msg(text));
+    }
+    any
+  }
+
+
+  // rebuild, keeping the cursor where it was
+  fn refresh_msg_view(&mut self) {
+    let pos = self.msg_doc.pos.clone();
+    self.msg_doc = Doc::new(
+      self.usr.layout.gemtext_to_text(&self.msgs.page()), 
+      &self.tab_page);
+    self.msg_doc.pos = pos;
+    self.clr_scr = true;
+  }
+
+
+  fn update_msg_view(&mut self, keycode: &KeyCode) 
+    -> Option<ViewMsg> 
+  {
+    let keys = &self.usr.keys;
+
+    if keycode == &keys.cancel || keycode == &keys.msg_view {
+      self.focus = Focus::Tab;
+      self.clr_scr = true;
+      Some(ViewMsg::Default)
+
+    } else if keycode == &keys.move_down {
+      self.msg_doc
+        .move_down(&self.tab_page, 1)
+        .then_some(ViewMsg::Default)
+
+    } else if keycode == &keys.move_up {
+      self.msg_doc
+        .move_up(&self.tab_page, 1)
+        .then_some(ViewMsg::Default)
+
+    } else {
+      None
+    }
+  }
 }
@@ -0,0 +1,139 @@
+// src/bookmark.rs
+
+use std::{
+  fs,
+  path::{Path},
+};
+use toml::{Table, Value};
+
+// module: bookmark
+//
+// a)   Keep urls worth coming back to
+//      in '.bookmarks'.
+//
+// b)   Write them out as a gemtext page,
+//      one heading per folder.
+//
+// (a) url + title + folder, (b) Vec<Bookmark> -> gemtext.
+
+
+pub const BOOKMARKS: &str = ".bookmarks";
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bookmark {
+  pub url:    String,
+  pub title:  String,
+  // empty for none
+  pub folder: String,
+}
+impl Bookmark {
+
+  fn from_value(value: &Value) -> Option<Self> {
+    let table = value.as_table()?;
+    Some(Self {
+      url:    table.get("url")?.as_str()?.into(),
+      title:  table.get("title")?.as_str()?.into(),
+      folder: table.get("folder")?.as_str()?.into(),
+    })
+  }
+
+
+  fn to_value(&self) -> Value {
+    let mut table = Table::new();
+    table.insert("url".into(),    Value::String(self.url.clone()));
+    table.insert("title".into(),  Value::String(self.title.clone()));
+    table.insert("folder".into(), Value::String(self.folder.clone()));
+    Value::Table(table)
+  }
+}
+
+
+// missing or broken file means no bookmarks
+pub fn load(dir: &Path) -> Vec<Bookmark> {
+  fs::read_to_string(dir.join(BOOKMARKS))
+    .ok()
+    .and_then(|txt| txt.parse::<Table>().ok())
+    .and_then(|table| table
+      .get("bookmarks")
+      .and_then(|v| v.as_array())
+      .map(|array| array
+        .iter()
+        .filter_map(Bookmark::from_value)
+        .collect()))
+    .unwrap_or_default()
+}
+
+
+fn save(dir: &Path, marks: &[Bookmark]) -> Result<(), String> {
+
+  let mut table = Table::new();
+  table.insert(
+    "bookmarks".into(),
+    Value::Array(marks.iter().map(Bookmark::to_value).collect()));
+
+  fs::write(dir.join(BOOKMARKS), table.to_string())
+    .map_err(|e| e.to_string())
+}
+
+
+// bookmarking a url twice moves it
+pub fn add(dir: &Path, mark: Bookmark) -> Result<(), String> {
+  let mut marks = load(dir);
+  marks.retain(|m| m.url != mark.url);
+  marks.push(mark);
+  save(dir, &marks)
+}
+
+
+pub fn remove(dir: &Path, url: &str) -> Result<(), String> {
+  let mut marks = load(dir);
+  marks.retain(|m| m.url != url);
+  save(dir, &marks)
+}
+
+
+pub fn rename(dir: &Path, url: &str, title: &str) -> Result<(), String> {
+  let mut marks = load(dir);
+  for m in marks.iter_mut().filter(|m| m.url == url) {
+    m.title = title.into();
+  }
+  save(dir, &marks)
+}
+
+
+pub fn find(dir: &Path, url: &str) -> Option<Bookmark> {
+  load(dir)
+    .into_iter()
+    .find(|m| m.url == url)
+}
+
+
+// loose bookmarks first, then a section per folder
+pub fn page(dir: &Path) -> String {
+
+  let marks = load(dir);
+  let mut text = String::from("# bookmarks\n\n");
+
+  if marks.is_empty() {
+    text.push_str("no bookmarks yet\n");
+    return text
+  }
+
+  let mut folders: Vec<&str> = marks
+    .iter()
+    .map(|m| m.folder.as_str())
+    .collect();
+  folders.sort();
+  folders.dedup();
+
+  for folder in folders {
+    if !folder.is_empty() {
+      text.push_str(&format!("\n## {}\n", folder));
+    }
+    for m in marks.iter().filter(|m| m.folder == folder) {
+      text.push_str(&format!("=> {} {}\n", m.url, m.title));
+    }
+  }
+  text
+}
@@ -20,6 +20,7 @@ use std::{
 pub enum InputType {
   Ack(KeyCode),
   Ask(KeyCode, KeyCode),
+  Choose(Vec<(KeyCode, String)>),
   Text(Editor, Pos),
 }
 
@@ -62,6 +63,16 @@ impl Dialog {
           .write_page(&self.input_page, writer)?;
       }
 
+      InputType::Choose(choices) => {
+        let line = choices
+          .iter()
+          .map(|(k, s)| format!("|{}| {}", k, s))
+          .collect::<Vec<String>>()
+          .join(" ");
+        Text::from(line.as_str())
+          .write_page(&self.input_page, writer)?;
+      }
+
       InputType::Text(editor, pos) => {
         editor.write_page(&self.input_page, writer)?;
         writer.queue(MoveTo(pos.x.cursor, pos.y.cursor))?;
@@ -146,6 +157,13 @@ impl Dialog {
             None
         }
       }
+
+      InputType::Choose(choices) => {
+        choices
+          .iter()
+          .position(|(k, _)| k == keycode)
+          .map(InputMsg::Choice)
+      }
     }
   }
 }
@@ -0,0 +1,53 @@
+// src/finger.rs
+
+use crate::{
+  gem::{GemDoc, StatusText},
+  util::{connect, read_body, Cancel, FetchError},
+};
+use percent_encoding::{percent_decode_str};
+use std::{
+  io::{Write},
+};
+use url::{Url};
+
+// module: finger
+//
+// Ask a host about a user on port 79,
+// show whatever it says as plain text.
+
+
+// 'finger://host/user' or 'finger://user@host'
+pub fn user(url: &Url) -> String {
+  let user = match url.username() {
+    "" => url.path().trim_start_matches('/'),
+    u  => u,
+  };
+  percent_decode_str(user)
+    .decode_utf8_lossy()
+    .to_string()
+}
+
+
+pub fn get_data(url: &Url, cancel: &Cancel) 
+  -> Result<Vec<u8>, FetchError> 
+{
+  let host = url.host_str().unwrap_or("");
+  let urlf = format!("{}:{}", host, url.port().unwrap_or(79));
+
+  let mut stream = connect(&urlf)?;
+  cancel.watch(&stream)?;
+
+  stream.write_all(format!("{}\r\n", user(url)).as_bytes())
+    .map_err(|e| e.to_string())?;
+
+  Ok(read_body(&mut stream, cancel)?)
+}
+
+
+pub fn get_doc(url: &Url, cancel: &Cancel) 
+  -> Result<GemDoc, FetchError> 
+{
+  let content = get_data(url, cancel)?;
+  let status = StatusText::parse("20 text/plain");
+  Ok(GemDoc::from_response(url, status, content))
+}
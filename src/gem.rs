@@ -3,27 +3,90 @@
 use crate::{
   util::{
     get_data, split_whitespace_once, Scheme, 
-    join_if_relative,
+    join_if_relative, FetchError, Cancel, decode,
   },
+  gopher,
+  finger,
+  spartan,
+  local,
+  about,
 };
 use url::{Url};
+use std::{
+  path::{Path},
+};
 
 pub struct GemDoc {
   pub url:    Url,
   pub status: StatusText,
   pub doc:    Vec<GemText>,
+  // things worth knowing about how doc was made
+  pub info:   Vec<String>,
+  // the body, if it isn't text
+  pub raw:    Vec<u8>,
+  // the body stopped short of its end
+  pub cut:    bool,
 }
 impl GemDoc {
-  pub fn new(url: &Url) -> Result<Self, String> {
+  pub fn new(url: &Url, dir: &Path, cancel: &Cancel) 
+    -> Result<Self, FetchError> 
+  {
+    let gemdoc = match Scheme::from(url) {
+      Scheme::Gopher => 
+        gopher::get_doc(url, cancel),
+
+      Scheme::Finger => 
+        finger::get_doc(url, cancel),
+
+      Scheme::Spartan => 
+        spartan::get_doc(url, cancel),
+
+      Scheme::File => 
+        Ok(local::get_doc(url)?),
+
+      Scheme::About => 
+        Ok(about::get_doc(url, dir)?),
+
+      _ => {
+        let (response, content) = get_data(url, dir, cancel)?;
+        let status = StatusText::parse(&response);
+        Ok(Self::from_response(url, status, content))
+      }
+    }?;
+    Ok(gemdoc)
+  }
 
-    let (response, content) = get_data(url)
-      .map_err(|e| e.to_string())?;
 
-    let status = StatusText::parse(&response);
+  // make a document out of a status and a body
+  pub fn from_response(url: &Url, 
+                       status: StatusText, 
+                       content: Vec<u8>) 
+    -> Self 
+  {
+    let mut info = vec![];
+    let mut raw = vec![];
+
+    let doc = match (&status.tag, &status.mime) {
+      (Status::Success, Some(mime)) if mime.is_text() => {
+        let (text, note) = 
+          decode(&content, mime.param("charset"));
+        info.extend(note);
+
+        if mime.is_gemtext() {
+          GemText::parse_doc(&text, url)
+        } else {
+          GemText::parse_plain(&text)
+        }
+      }
 
-    let doc = match status.tag {
-      Status::Success => 
-        GemText::parse_doc(&content, url),
+      (Status::Success, Some(mime)) => {
+        let msg = format!(
+          "{} is not text, {} bytes", 
+          mime.essence(),
+          content.len());
+        raw = content;
+        vec![GemText::new(GemTag::Text, &msg)]
+      }
 
       _ => {
         let msg = format!(
@@ -33,12 +96,36 @@ impl GemDoc {
         vec![GemText::new(GemTag::Text, &msg)]
       }
     };
-    let gem_doc = Self {
+    Self {
       url:    url.clone(),
       status: status,
       doc:    doc,
-    };
-    Ok(gem_doc)
+      info,
+      raw,
+      cut:    false,
+    }
+  }
+
+
+  // say so when the body stopped at max bytes
+  pub fn truncate(&mut self, max: usize) {
+    let note = format!("truncated at {} bytes", max);
+    self.doc.push(GemText::new(GemTag::Text, ""));
+    self.doc.push(GemText::new(GemTag::Text, &format!("({})", note)));
+    self.info.push(note);
+    self.cut = true;
+  }
+
+
+  // first heading, or the url if there isn't one
+  pub fn title(&self) -> String {
+    self.doc
+      .iter()
+      .find(|gtxt| matches!(gtxt.tag, 
+        GemTag::HeadingOne | GemTag::HeadingTwo | GemTag::HeadingThree))
+      .map(|gtxt| gtxt.txt.trim().to_string())
+      .filter(|title| !title.is_empty())
+      .unwrap_or(self.url.to_string())
   }
 }
 
@@ -82,6 +169,14 @@ impl GemText {
     vec
   }
 
+  // every line as it is
+  pub fn parse_plain(text_str: &str) -> Vec<Self> {
+    text_str
+      .lines()
+      .map(|line| Self::new(GemTag::PreFormat, line))
+      .collect()
+  }
+
   pub fn parse_formatted(line: &str, source: &Url) -> Self {
     // look for 3 character symbols
     if let Some(("###", text)) = 
@@ -110,6 +205,22 @@ impl GemText {
                 link_str.into())
         }
 
+      } else if symbol == "=:" && source.scheme() == "spartan" {
+
+        // spartan asks for input with a prompt line
+        let (url_str, link_str) = 
+          split_whitespace_once(text);
+
+        match join_if_relative(source, url_str) {
+          Ok(url) =>
+            return Self::new(GemTag::Prompt(url), link_str),
+          Err(s) => 
+            return Self::new(
+              GemTag::BadLink(
+                s.to_string()), 
+                link_str)
+        }
+
       } else if symbol == "##" {
         return Self::new(GemTag::HeadingTwo, text.into())
       }
@@ -143,14 +254,17 @@ pub enum GemTag {
   PreFormat,
   Link(Scheme, Url),
   BadLink(String),
+  Prompt(Url),
   ListItem,
   Quote,
 } 
 
 #[derive(Debug, Clone)]
 pub struct StatusText {
-  pub tag: Status, 
-  pub txt: String,
+  pub tag:  Status, 
+  pub txt:  String,
+  // only for success
+  pub mime: Option<MimeType>,
 }
 impl StatusText {
   pub fn parse(line: &str) -> Self {
@@ -158,12 +272,82 @@ impl StatusText {
     let (code_str, msg) = split_whitespace_once(line);
     let status = Status::from(code_str);
 
+    // a bare status code comes back twice
+    let meta = if msg == code_str {""} else {msg};
+
+    let mime = match status {
+      Status::Success => Some(MimeType::parse(meta)),
+      _ => None,
+    };
+
+    Self {
+      tag:  status,
+      txt:  msg.into(),
+      mime,
+    }
+  }
+}
+
+// 'type/subtype; key=value; ...'
+#[derive(Debug, Clone, PartialEq)]
+pub struct MimeType {
+  pub kind:    String,
+  pub subtype: String,
+  pub params:  Vec<(String, String)>,
+}
+impl Default for MimeType {
+  fn default() -> Self {
     Self {
-      tag: status,
-      txt: msg.into()
+      kind:    "text".into(),
+      subtype: "gemini".into(),
+      params:  vec![],
     }
   }
 }
+impl MimeType {
+  // empty meta means text/gemini
+  pub fn parse(meta: &str) -> Self {
+
+    let mut parts = meta.split(';');
+
+    let Some((kind, subtype)) = parts
+      .next()
+      .and_then(|essence| essence.trim().split_once('/'))
+      else {return Self::default()};
+
+    let params = parts
+      .filter_map(|p| p.split_once('='))
+      .map(|(k, v)| (
+        k.trim().to_lowercase(), 
+        v.trim().trim_matches('"').into()))
+      .collect();
+
+    Self {
+      kind:    kind.trim().to_lowercase(),
+      subtype: subtype.trim().to_lowercase(),
+      params,
+    }
+  }
+
+  pub fn essence(&self) -> String {
+    format!("{}/{}", self.kind, self.subtype)
+  }
+
+  pub fn param(&self, key: &str) -> Option<&str> {
+    self.params
+      .iter()
+      .find(|(k, _)| k == key)
+      .map(|(_, v)| v.as_str())
+  }
+
+  pub fn is_text(&self) -> bool {
+    self.kind == "text"
+  }
+
+  pub fn is_gemtext(&self) -> bool {
+    self.is_text() && self.subtype == "gemini"
+  }
+}
 
 #[derive(Debug, Clone)]
 pub enum Status {
@@ -191,6 +375,46 @@ pub enum Status {
   Unknown(u8),
   Junk(String),
 }
+impl Status {
+  // answered in a dialog, with nothing to show
+  pub fn is_prompt(&self) -> bool {
+    matches!(self, 
+      Status::InputExpected | 
+      Status::InputExpectedSensitive |
+      Status::CertRequiredClient |
+      Status::CertRequiredTransient |
+      Status::CertRequiredAuthorized)
+  }
+
+  // short name, for the tab overview
+  pub fn label(&self) -> String {
+    match self {
+      Status::InputExpected           => "input".into(),
+      Status::InputExpectedSensitive  => "secret input".into(),
+      Status::Success                 => "ok".into(),
+      Status::RedirectTemporary       => "redirect".into(),
+      Status::RedirectPermanent       => "moved".into(),
+      Status::FailTemporary           => "failed".into(),
+      Status::FailServerUnavailable   => "unavailable".into(),
+      Status::FailCGIError            => "cgi error".into(),
+      Status::FailProxyError          => "proxy error".into(),
+      Status::FailSlowDown            => "slow down".into(),
+      Status::FailPermanent           => "failed".into(),
+      Status::FailNotFound            => "not found".into(),
+      Status::FailGone                => "gone".into(),
+      Status::FailProxyRequestRefused => "proxy refused".into(),
+      Status::FailBadRequest          => "bad request".into(),
+      Status::CertRequiredClient      => "certificate wanted".into(),
+      Status::CertRequiredTransient   => "certificate wanted".into(),
+      Status::CertRequiredAuthorized  => "certificate wanted".into(),
+      Status::CertNotAccepted         => "certificate refused".into(),
+      Status::FutureCertRejected      => "certificate not valid yet".into(),
+      Status::ExpiredCertRejected     => "certificate expired".into(),
+      Status::Unknown(u)              => format!("status {}", u),
+      Status::Junk(_)                 => "bad response".into(),
+    }
+  }
+}
 impl From<&str> for Status {
   fn from(item: &str) -> Status {
     match item.parse::<u8>()
@@ -225,3 +449,27 @@ impl From<&str> for Status {
     }
   }
 }
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mime_essence_and_params() {
+    let mime = MimeType::parse("Text/Plain; Charset=\"ISO-8859-1\"; lang=en");
+    assert_eq!(mime.essence(), "text/plain");
+    assert_eq!(mime.param("charset"), Some("ISO-8859-1"));
+    assert_eq!(mime.param("lang"), Some("en"));
+    assert_eq!(mime.param("format"), None);
+    assert!(mime.is_text());
+    assert!(!mime.is_gemtext());
+  }
+
+  #[test]
+  fn mime_defaults_to_gemtext() {
+    assert!(MimeType::parse("").is_gemtext());
+    assert!(MimeType::parse("nonsense").is_gemtext());
+    assert!(!MimeType::parse("image/png").is_text());
+  }
+}
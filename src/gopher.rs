@@ -0,0 +1,220 @@
+// src/gopher.rs
+
+use crate::{
+  gem::{GemDoc, GemText, GemTag, StatusText},
+  util::{connect, read_body, decode, Cancel, FetchError, Scheme},
+};
+use percent_encoding::{
+  utf8_percent_encode, percent_decode_str, AsciiSet, NON_ALPHANUMERIC,
+};
+use std::{
+  io::{Write},
+};
+use url::{Url};
+
+// module: gopher
+//
+// a)   Fetch over plain tcp.
+//
+// b)   Turn gophermaps into gemtext.
+//
+// (a) selector -> bytes, (b) menu -> Vec<GemText>.
+
+
+// what may stay as it is in a selector put in a url path,
+// so '?', '#' and '%' stay part of the selector
+const SELECTOR: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'/')
+  .remove(b'.')
+  .remove(b'-')
+  .remove(b'_')
+  .remove(b'~');
+
+
+// item type and selector from a url path like '/1/dir'
+pub fn item_selector(url: &Url) -> (char, String) {
+
+  let path = percent_decode_str(url.path())
+    .decode_utf8_lossy()
+    .to_string();
+
+  let mut chars = path.trim_start_matches('/').chars();
+
+  match chars.next() {
+    Some(item) => (item, chars.collect()),
+    None       => ('1', "".into()),
+  }
+}
+
+
+// mime type to report for an item type
+fn item_mime(item: char) -> &'static str {
+  match item {
+    '1' | '7' => "text/gemini",
+    '0'       => "text/plain",
+    'h'       => "text/html",
+    'g'       => "image/gif",
+    'I'       => "image/*",
+    's'       => "audio/*",
+    _         => "application/octet-stream",
+  }
+}
+
+
+pub fn get_data(url: &Url, cancel: &Cancel) 
+  -> Result<Vec<u8>, FetchError> 
+{
+  let host = url.host_str().unwrap_or("");
+  let urlf = format!("{}:{}", host, url.port().unwrap_or(70));
+
+  let (item, mut selector) = item_selector(url);
+
+  // search terms follow a tab,
+  // anything else was a '?' typed into the selector
+  if let Some(query) = url.query() {
+    selector.push(if item == '7' {'\t'} else {'?'});
+    selector.push_str(
+      &percent_decode_str(query).decode_utf8_lossy());
+  }
+
+  let mut stream = connect(&urlf)?;
+  cancel.watch(&stream)?;
+
+  stream.write_all(format!("{}\r\n", selector).as_bytes())
+    .map_err(|e| e.to_string())?;
+
+  Ok(read_body(&mut stream, cancel)?)
+}
+
+
+pub fn get_doc(url: &Url, cancel: &Cancel) 
+  -> Result<GemDoc, FetchError> 
+{
+  let (item, _) = item_selector(url);
+
+  // searches need something to search for
+  if item == '7' && url.query().is_none() {
+    let status = StatusText::parse("10 search");
+    return Ok(GemDoc::from_response(url, status, vec![]))
+  }
+
+  let content = get_data(url, cancel)?;
+  let status = StatusText::parse(
+    &format!("20 {}", item_mime(item)));
+
+  match item {
+    '1' | '7' => {
+      let (text, _) = decode(&content, None);
+      let mut gdoc = GemDoc::from_response(url, status, vec![]);
+      gdoc.doc = parse_menu(&text);
+      Ok(gdoc)
+    }
+    _ => 
+      Ok(GemDoc::from_response(url, status, content)),
+  }
+}
+
+
+pub fn parse_menu(text: &str) -> Vec<GemText> {
+  text
+    .lines()
+    .take_while(|line| *line != ".")
+    .filter(|line| !line.is_empty())
+    .map(parse_menu_line)
+    .collect()
+}
+
+
+// 'Tdisplay<tab>selector<tab>host<tab>port'
+pub fn parse_menu_line(line: &str) -> GemText {
+
+  let mut chars = line.chars();
+  let item = chars.next().unwrap_or('i');
+  let rest: String = chars.collect();
+
+  let mut fields = rest.split('\t');
+  let display  = fields.next().unwrap_or("");
+  let selector = fields.next().unwrap_or("");
+  let host     = fields.next().unwrap_or("");
+  let port     = fields.next().unwrap_or("70").trim();
+
+  match item {
+    'i' => 
+      GemText::new(GemTag::PreFormat, display),
+
+    '3' => 
+      GemText::new(GemTag::Text, display),
+
+    // links to other protocols
+    'h' if selector.starts_with("URL:") => {
+      let target = &selector[4..];
+      match Url::parse(target) {
+        Ok(url) => GemText::new(
+          GemTag::Link(Scheme::from(&url), url), display),
+        Err(e) => GemText::new(
+          GemTag::BadLink(e.to_string()), display),
+      }
+    }
+
+    item => {
+      let target = format!(
+        "gopher://{}:{}/{}{}", 
+        host, port, item, utf8_percent_encode(selector, SELECTOR));
+      match Url::parse(&target) {
+        Ok(url) => GemText::new(
+          GemTag::Link(Scheme::Gopher, url), display),
+        Err(e) => GemText::new(
+          GemTag::BadLink(e.to_string()), display),
+      }
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+  }
+
+  #[test]
+  fn menu_lines() {
+    assert_eq!(
+      parse_menu_line("iwelcome\tfake\t(NULL)\t0"),
+      GemText::new(GemTag::PreFormat, "welcome"));
+    assert_eq!(
+      parse_menu_line("3no such thing\t\terror.host\t1"),
+      GemText::new(GemTag::Text, "no such thing"));
+    assert_eq!(
+      parse_menu_line("1docs\t/docs dir\texample.org\t7070"),
+      GemText::new(
+        GemTag::Link(
+          Scheme::Gopher, 
+          url("gopher://example.org:7070/1/docs%20dir")),
+        "docs"));
+    assert_eq!(
+      parse_menu_line("hweb\tURL:https://example.org/\texample.org\t70"),
+      GemText::new(
+        GemTag::Link(Scheme::Http, url("https://example.org/")),
+        "web"));
+  }
+
+  #[test]
+  fn selector_keeps_query_chars() {
+    let line = parse_menu_line("0file\t/a?b#c%d\texample.org\t70");
+    let GemTag::Link(_, link) = line.tag 
+      else {panic!("not a link")};
+    assert_eq!(item_selector(&link), ('0', "/a?b#c%d".into()));
+  }
+
+  #[test]
+  fn item_selector_defaults_to_menu() {
+    assert_eq!(item_selector(&url("gopher://example.org")), ('1', "".into()));
+    assert_eq!(item_selector(&url("gopher://example.org/")), ('1', "".into()));
+    assert_eq!(
+      item_selector(&url("gopher://example.org/7/search")), 
+      ('7', "/search".into()));
+  }
+}
@@ -0,0 +1,101 @@
+// src/history.rs
+
+use crate::{
+  util::{now, day},
+};
+use std::{
+  fs::{self, OpenOptions},
+  io::{Write},
+  path::{Path},
+};
+use url::{Url};
+
+// module: history
+//
+// a)   Append every page visited to '.history',
+//      one 'time<tab>url<tab>title' line each.
+//
+// b)   Write the log out as gemtext,
+//      newest first, a heading per day.
+//
+// (a) url + title -> line, (b) lines -> gemtext.
+
+
+pub const HISTORY: &str = ".history";
+
+
+pub struct Visit {
+  pub time:  i64,
+  pub url:   String,
+  pub title: String,
+}
+
+
+pub fn append(dir: &Path, url: &Url, title: &str) -> Result<(), String> {
+
+  let line = format!(
+    "{}\t{}\t{}\n",
+    now(),
+    url,
+    title.replace(['\t', '\n'], " "));
+
+  OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(dir.join(HISTORY))
+    .and_then(|mut file| file.write_all(line.as_bytes()))
+    .map_err(|e| e.to_string())
+}
+
+
+// broken lines are skipped
+pub fn load(dir: &Path) -> Vec<Visit> {
+  fs::read_to_string(dir.join(HISTORY))
+    .unwrap_or_default()
+    .lines()
+    .filter_map(|line| {
+      let mut parts = line.splitn(3, '\t');
+      Some(Visit {
+        time:  parts.next()?.parse().ok()?,
+        url:   parts.next()?.into(),
+        title: parts.next().unwrap_or("").into(),
+      })
+    })
+    .collect()
+}
+
+
+// every visit whose url, title or day contains filter
+pub fn page(dir: &Path, filter: Option<&str>) -> String {
+
+  let mut text = match filter {
+    Some(f) => format!("# history matching {}\n\n", f),
+    None    => String::from("# history\n\n"),
+  };
+  text.push_str("=> about:filter-history filter\n");
+  text.push_str("=> about:history everything\n");
+
+  let filter = filter.map(|f| f.to_lowercase());
+  let mut last_day = String::new();
+
+  for visit in load(dir).iter().rev() {
+
+    let day = day(visit.time);
+
+    if let Some(f) = &filter {
+      let hit = [&visit.url, &visit.title, &day]
+        .iter()
+        .any(|s| s.to_lowercase().contains(f.as_str()));
+      if !hit {
+        continue
+      }
+    }
+
+    if day != last_day {
+      text.push_str(&format!("\n## {}\n", day));
+      last_day = day;
+    }
+    text.push_str(&format!("=> {} {}\n", visit.url, visit.title));
+  }
+  text
+}
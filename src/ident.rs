@@ -0,0 +1,229 @@
+// src/ident.rs
+
+use std::{
+  fs::{self, File},
+  io::{self, Write},
+  path::{Path},
+};
+#[cfg(unix)]
+use std::{
+  fs::{DirBuilder, OpenOptions, Permissions},
+  os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt},
+};
+use native_tls::Identity;
+use rcgen::{CertificateParams, DnType, KeyPair};
+use toml::{Table, Value};
+use url::{Url};
+
+// module: ident
+//
+// a)   Make self-signed client certificates
+//      and keep them in 'identities/'.
+//
+// b)   Remember which identity answers for
+//      which url prefix in '.identities'.
+//
+// (a) name -> certificate, (b) url -> name.
+
+
+pub const IDENTITIES: &str = "identities";
+pub const BINDINGS:   &str = ".identities";
+
+
+// names of every identity on disk
+pub fn names(dir: &Path) -> Vec<String> {
+
+  let mut names: Vec<String> = fs::read_dir(dir.join(IDENTITIES))
+    .map(|entries| entries
+      .filter_map(|e| e.ok())
+      .filter_map(|e| {
+        let path = e.path();
+        match path.extension() {
+          Some(ext) if ext == "crt" => path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string()),
+          _ => None,
+        }
+      })
+      .collect())
+    .unwrap_or_default();
+
+  names.sort();
+  names
+}
+
+
+// generate a certificate and key called name
+pub fn create(dir: &Path, name: &str) -> Result<(), String> {
+
+  if name.is_empty() || !name.chars()
+    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+  {
+    return Err(
+      format!("identity name {:?} should be letters, \
+               digits, '-' or '_'", name))
+  }
+
+  let mut params = CertificateParams::new(vec![])
+    .map_err(|e| e.to_string())?;
+  params.distinguished_name
+    .push(DnType::CommonName, name);
+
+  let key = KeyPair::generate()
+    .map_err(|e| e.to_string())?;
+  let cert = params.self_signed(&key)
+    .map_err(|e| e.to_string())?;
+
+  let path = dir.join(IDENTITIES);
+  private_dir(&path)
+    .map_err(|e| e.to_string())?;
+  fs::write(path.join(format!("{}.crt", name)), cert.pem())
+    .map_err(|e| e.to_string())?;
+
+  private_file(&path.join(format!("{}.key", name)))
+    .and_then(|mut file| file.write_all(key.serialize_pem().as_bytes()))
+    .map_err(|e| e.to_string())
+}
+
+
+// private keys are for our eyes only,
+// even where an older version left things open
+#[cfg(unix)]
+fn private_dir(path: &Path) -> io::Result<()> {
+  DirBuilder::new()
+    .recursive(true)
+    .mode(0o700)
+    .create(path)?;
+  fs::set_permissions(path, Permissions::from_mode(0o700))
+}
+
+
+#[cfg(unix)]
+fn private_file(path: &Path) -> io::Result<File> {
+  let file = OpenOptions::new()
+    .write(true)
+    .create(true)
+    .truncate(true)
+    .mode(0o600)
+    .open(path)?;
+  file.set_permissions(Permissions::from_mode(0o600))?;
+  Ok(file)
+}
+
+
+// elsewhere the user's home is the only guard
+#[cfg(not(unix))]
+fn private_dir(path: &Path) -> io::Result<()> {
+  fs::create_dir_all(path)
+}
+
+
+#[cfg(not(unix))]
+fn private_file(path: &Path) -> io::Result<File> {
+  File::create(path)
+}
+
+
+pub fn load(dir: &Path, name: &str) -> Result<Identity, String> {
+
+  let path = dir.join(IDENTITIES);
+  let cert = fs::read(path.join(format!("{}.crt", name)))
+    .map_err(|e| format!("identity {}: {}", name, e))?;
+  let key = fs::read(path.join(format!("{}.key", name)))
+    .map_err(|e| format!("identity {}: {}", name, e))?;
+
+  Identity::from_pkcs8(&cert, &key)
+    .map_err(|e| format!("identity {}: {}", name, e))
+}
+
+
+fn read_bindings(dir: &Path) -> Table {
+  fs::read_to_string(dir.join(BINDINGS))
+    .ok()
+    .and_then(|txt| txt.parse::<Table>().ok())
+    .unwrap_or_default()
+}
+
+
+// use identity name for url and everything beneath it
+pub fn bind(dir: &Path, url: &Url, name: &str)
+  -> Result<(), String>
+{
+  let mut prefix = url.clone();
+  prefix.set_query(None);
+  prefix.set_fragment(None);
+
+  let mut table = read_bindings(dir);
+  table.insert(prefix.to_string(), Value::String(name.into()));
+
+  fs::write(dir.join(BINDINGS), table.to_string())
+    .map_err(|e| e.to_string())
+}
+
+
+// url is prefix or somewhere beneath it: same scheme, host
+// and port, and a path going on from prefix's at a '/'
+fn covers(prefix: &Url, url: &Url) -> bool {
+
+  if prefix.scheme() != url.scheme() 
+    || prefix.host_str() != url.host_str() 
+    || prefix.port() != url.port() 
+  {
+    return false
+  }
+
+  let base = match prefix.path() {"" => "/", p => p};
+  let path = match url.path()    {"" => "/", p => p};
+
+  match path.strip_prefix(base) {
+    Some(rest) => 
+      rest.is_empty() || base.ends_with('/') || rest.starts_with('/'),
+    None => 
+      false,
+  }
+}
+
+
+// name of the identity bound to the longest prefix of url
+pub fn find(dir: &Path, url: &Url) -> Option<String> {
+  read_bindings(dir)
+    .iter()
+    .filter_map(|(prefix, name)| 
+      Some((Url::parse(prefix).ok()?, name.as_str()?)))
+    .filter(|(prefix, _)| covers(prefix, url))
+    .max_by_key(|(prefix, _)| prefix.path().len())
+    .map(|(_, name)| name.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+  }
+
+  #[test]
+  fn covers_same_host_only() {
+    let prefix = url("gemini://example.com");
+    assert!(covers(&prefix, &url("gemini://example.com/")));
+    assert!(covers(&prefix, &url("gemini://example.com/a/b")));
+    assert!(!covers(&prefix, &url("gemini://example.com.evil.org/")));
+    assert!(!covers(&prefix, &url("gemini://example.com:1966/")));
+    assert!(!covers(&prefix, &url("titan://example.com/")));
+  }
+
+  #[test]
+  fn covers_whole_segments() {
+    let prefix = url("gemini://example.com/app");
+    assert!(covers(&prefix, &url("gemini://example.com/app")));
+    assert!(covers(&prefix, &url("gemini://example.com/app/login")));
+    assert!(!covers(&prefix, &url("gemini://example.com/application")));
+    assert!(!covers(&prefix, &url("gemini://example.com/")));
+
+    let prefix = url("gemini://example.com/app/");
+    assert!(covers(&prefix, &url("gemini://example.com/app/x")));
+    assert!(!covers(&prefix, &url("gemini://example.com/app")));
+  }
+}
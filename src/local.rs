@@ -0,0 +1,104 @@
+// src/local.rs
+
+use crate::{
+  gem::{GemDoc, StatusText},
+  util::{guess_mime},
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::{
+  fs,
+  path::{Path},
+  time::{SystemTime},
+};
+use url::{Url};
+
+// module: local
+//
+// a)   Read files off the disk for file:// urls.
+//
+// b)   Write a gemtext index for directories.
+//
+// (a) path -> bytes, (b) directory -> gemtext.
+
+
+// what may stay as it is in a link to a file name
+const NAME: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'.')
+  .remove(b'-')
+  .remove(b'_')
+  .remove(b'~');
+
+
+// a path from the command line, or anything that's already a url
+pub fn file_url(arg: &str) -> Result<Url, String> {
+  if let Ok(url) = Url::parse(arg) {
+    return Ok(url)
+  }
+  let path = fs::canonicalize(arg)
+    .map_err(|e| format!("{}: {}", arg, e))?;
+  Url::from_file_path(&path)
+    .map_err(|_| format!("{} is not a local path", path.display()))
+}
+
+
+// when the file behind url last changed, 
+// None for anything that isn't on disk
+pub fn modified(url: &Url) -> Option<SystemTime> {
+  if url.scheme() != "file" {
+    return None
+  }
+  url.to_file_path()
+    .ok()
+    .and_then(|path| fs::metadata(path).ok())
+    .and_then(|meta| meta.modified().ok())
+}
+
+
+// a link per entry, directories first
+pub fn index(path: &Path) -> Result<String, String> {
+
+  let mut entries: Vec<(bool, String)> = fs::read_dir(path)
+    .map_err(|e| format!("{}: {}", path.display(), e))?
+    .filter_map(|e| e.ok())
+    .map(|e| (
+      !e.path().is_dir(),
+      e.file_name().to_string_lossy().to_string()))
+    .collect();
+  entries.sort();
+
+  let mut text = format!("# {}\n\n=> ../ ..\n", path.display());
+
+  for (is_file, name) in entries {
+    let slash = if is_file {""} else {"/"};
+    text.push_str(&format!(
+      "=> {}{} {}{}\n",
+      utf8_percent_encode(&name, NAME), slash,
+      name, slash));
+  }
+  Ok(text)
+}
+
+
+pub fn get_doc(url: &Url) -> Result<GemDoc, String> {
+
+  let path = url.to_file_path()
+    .map_err(|_| format!("{} is not a local path", url))?;
+
+  if path.is_dir() {
+    // links in the index are relative to the directory
+    let mut url = url.clone();
+    if !url.path().ends_with('/') {
+      url.set_path(&format!("{}/", url.path()));
+    }
+    let status = StatusText::parse("20 text/gemini");
+    let content = index(&path)?.into_bytes();
+    Ok(GemDoc::from_response(&url, status, content))
+
+  } else {
+    let content = fs::read(&path)
+      .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let status = StatusText::parse(
+      &format!("20 {}", guess_mime(&path)));
+    Ok(GemDoc::from_response(url, status, content))
+  }
+}
@@ -14,6 +14,19 @@ mod app;
 mod tab;
 mod text;
 mod dlg;
+mod tofu;
+mod ident;
+mod gopher;
+mod finger;
+mod spartan;
+mod titan;
+mod local;
+mod moved;
+mod bookmark;
+mod about;
+mod history;
+mod session;
+mod msglog;
 
 use crate::{
   app::App,
@@ -22,10 +35,28 @@ use crossterm::{
   QueueableCommand, terminal, event,
 };
 use std::{
+  env, process,
+  time::{Duration},
   io::{self, stdout, Write}
 };
+use tokio::runtime::Runtime;
 
 fn main() -> io::Result<()> {
+
+  // 'gem capsule/index.gmi' previews a local file
+  let url = match env::args().nth(1).map(|arg| local::file_url(&arg)) {
+    Some(Ok(url)) => Some(url.to_string()),
+    Some(Err(e)) => {
+      eprintln!("{}", e);
+      process::exit(1)
+    }
+    None => None,
+  };
+
+  // requests run on the runtime, the ui stays here
+  let runtime = Runtime::new()?;
+  let _guard = runtime.enter();
+
   terminal::enable_raw_mode()?;
 
   let mut stdout = stdout();
@@ -35,17 +66,27 @@ fn main() -> io::Result<()> {
     .queue(terminal::DisableLineWrap)?;
 
   let (w, h) = terminal::size()?;
-  let mut ui = App::init(".gemset", w, h);
+  let mut ui = App::init(".gemset", url.as_deref(), w, h);
 
   ui.view(&mut stdout)?;
 
   while !ui.quit {
-    if ui.update(event::read()?) {
+
+    // wait a little for input, then check on requests
+    let mut redraw = false;
+    if event::poll(Duration::from_millis(50))? {
+      redraw = ui.update(event::read()?);
+    }
+    if ui.poll() || redraw {
       ui.view(&mut stdout)?;
     }
   }
 
   terminal::disable_raw_mode()?;
   stdout.queue(terminal::LeaveAlternateScreen)?;
-  stdout.flush()
+  stdout.flush()?;
+
+  // don't wait on requests nobody will read
+  runtime.shutdown_background();
+  Ok(())
 }
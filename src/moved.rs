@@ -0,0 +1,143 @@
+// src/moved.rs
+
+use crate::{
+  gem::{GemText, GemTag},
+  util::{Scheme},
+};
+use std::{
+  fs,
+  path::{Path},
+};
+use toml::{Table, Value};
+use url::{Url};
+
+// module: moved
+//
+// a)   Write down every permanent redirect
+//      in '.redirects'.
+//
+// b)   Send urls and links straight to where
+//      they moved.
+//
+// (a) old -> new, (b) url -> url.
+
+
+pub const REDIRECTS: &str = ".redirects";
+
+// lookups before giving up on a chain of moves
+const MAX_MOVES: usize = 5;
+
+
+fn read_moves(dir: &Path) -> Table {
+  fs::read_to_string(dir.join(REDIRECTS))
+    .ok()
+    .and_then(|txt| txt.parse::<Table>().ok())
+    .unwrap_or_default()
+}
+
+
+pub fn remember(dir: &Path, from: &Url, to: &Url)
+  -> Result<(), String>
+{
+  let mut table = read_moves(dir);
+  table.insert(from.to_string(), Value::String(to.to_string()));
+
+  fs::write(dir.join(REDIRECTS), table.to_string())
+    .map_err(|e| e.to_string())
+}
+
+
+// where url lives now, following moves of moves
+fn lookup(table: &Table, url: &Url) -> Url {
+
+  let mut url = url.clone();
+  let mut seen = vec![];
+
+  while seen.len() < MAX_MOVES {
+    let Some(to) = table
+      .get(url.as_str())
+      .and_then(|v| v.as_str())
+      .and_then(|s| Url::parse(s).ok())
+      else {break};
+
+    seen.push(url.clone());
+    if seen.contains(&to) {
+      break
+    }
+    url = to;
+  }
+  url
+}
+
+
+pub fn resolve(dir: &Path, url: &Url) -> Url {
+  lookup(&read_moves(dir), url)
+}
+
+
+// point links at where they moved
+pub fn rewrite(dir: &Path, doc: &mut [GemText]) {
+
+  let table = read_moves(dir);
+  if table.is_empty() {
+    return
+  }
+
+  for gtxt in doc.iter_mut() {
+    if let GemTag::Link(scheme, url) = &mut gtxt.tag {
+      *url = lookup(&table, url);
+      *scheme = Scheme::from(&*url);
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+  }
+
+  fn table(moves: &[(&str, &str)]) -> Table {
+    moves
+      .iter()
+      .map(|(from, to)| (from.to_string(), Value::String(to.to_string())))
+      .collect()
+  }
+
+  #[test]
+  fn follows_moves_of_moves() {
+    let moves = table(&[
+      ("gemini://a.org/", "gemini://b.org/"),
+      ("gemini://b.org/", "gemini://c.org/"),
+    ]);
+    assert_eq!(lookup(&moves, &url("gemini://a.org/")), url("gemini://c.org/"));
+    assert_eq!(lookup(&moves, &url("gemini://x.org/")), url("gemini://x.org/"));
+  }
+
+  #[test]
+  fn stops_at_a_loop() {
+    let moves = table(&[
+      ("gemini://a.org/", "gemini://b.org/"),
+      ("gemini://b.org/", "gemini://a.org/"),
+    ]);
+    assert_eq!(lookup(&moves, &url("gemini://a.org/")), url("gemini://b.org/"));
+
+    let moves = table(&[("gemini://a.org/", "gemini://a.org/")]);
+    assert_eq!(lookup(&moves, &url("gemini://a.org/")), url("gemini://a.org/"));
+  }
+
+  #[test]
+  fn stops_after_max_moves() {
+    let moves: Table = (0..2 * MAX_MOVES)
+      .map(|i| (
+        format!("gemini://{}.org/", i), 
+        Value::String(format!("gemini://{}.org/", i + 1))))
+      .collect();
+    assert_eq!(
+      lookup(&moves, &url("gemini://0.org/")), 
+      url(&format!("gemini://{}.org/", MAX_MOVES)));
+  }
+}
@@ -1,8 +1,15 @@
 // src/msg.rs
 
+use crate::{
+  tofu::{HostCert},
+  usr::{UserHandler},
+};
+
 #[derive(Clone, Debug)]
 pub enum ViewMsg {
   Default, 
+  // the tab put up a whole new screen
+  Redraw, 
   Global, 
   ReloadUser, 
   Msg(String), 
@@ -11,8 +18,61 @@ pub enum ViewMsg {
   CycleRight, 
   DeleteMe, 
   NewTab, 
-  Reply,
+  // url expecting input
+  Reply(String),
+  // url expecting input nobody should see
+  SecretReply(String),
   Go(String), 
+  // navigate within the current tab
+  Follow(String), 
+  // same, with a query nobody should see
+  FollowSecret(String), 
+  // next hop of a redirect the user agreed to,
+  // the history entry it was revisiting, and if it's for good
+  Redirect(String, Option<usize>, bool),
+  // one message per choice of a Choose dialog
+  Choose(Vec<ViewMsg>),
+  // url, host, certificate
+  TrustOnce(String, String, HostCert),
+  TrustAlways(String, String, HostCert),
+  // url that asked for a client certificate
+  Identify(String),
+  // url, identity name
+  UseIdentity(String, String),
+  // ask for a file name
+  Download,
+  // file name in download directory
+  Save(String),
+  // handler, url, file
+  Open(UserHandler, String, String),
+  // ask for a titan url
+  UploadTo, 
+  // titan url, ask where the data comes from
+  Titan(String),
+  // titan url, ask for a local file
+  PickFile(String),
+  // titan url, ask for the text itself
+  Compose(String),
+  // titan url, local file
+  AttachFile(String, String),
+  // titan url, mime, data
+  Attach(String, String, Vec<u8>),
+  // titan url, mime, data, ask for a token
+  Token(String, String, Vec<u8>),
+  // titan url, mime, data, token
+  Upload(String, String, Vec<u8>, String),
+  // url, title, ask for a folder
+  Bookmark(String, String),
+  // url, title, folder
+  AddBookmark(String, String, String),
+  // url
+  Unbookmark(String),
+  // url, ask for a new title
+  Rename(String),
+  // url, new title
+  Retitle(String, String),
+  // bring back the tabs from last time
+  Restore,
 }
 #[derive(Clone, Debug)]
 pub enum InputMsg {
@@ -21,6 +81,7 @@ pub enum InputMsg {
   Ack, 
   Yes, 
   No, 
+  Choice(usize),
   Text(String),
 }
 // view currently in use
@@ -28,4 +89,8 @@ pub enum InputMsg {
 pub enum Focus {
   Tab,
   Global,
+  // every tab, one per line
+  TabView,
+  // the message log
+  MsgView,
 }
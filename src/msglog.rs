@@ -0,0 +1,70 @@
+// src/msglog.rs
+
+use crate::{
+  gem::{GemText, GemTag},
+  util::{now, day, clock},
+};
+use std::{
+  collections::{VecDeque},
+};
+
+// module: msglog
+//
+// a)   Keep every message the app showed,
+//      with the time it came in.
+//
+// b)   Write them out as gemtext,
+//      newest first, a heading per day.
+//
+// (a) String -> Entry, (b) Vec<Entry> -> Vec<GemText>.
+
+
+// oldest entries go once there are this many
+const MAX_ENTRIES: usize = 1000;
+
+
+pub struct Entry {
+  pub time: i64,
+  pub text: String,
+}
+
+
+#[derive(Default)]
+pub struct MsgLog {
+  pub entries: VecDeque<Entry>,
+}
+impl MsgLog {
+
+  pub fn push(&mut self, text: &str) {
+    if self.entries.len() == MAX_ENTRIES {
+      self.entries.pop_front();
+    }
+    self.entries.push_back(Entry {time: now(), text: text.into()});
+  }
+
+
+  pub fn page(&self) -> Vec<GemText> {
+
+    let mut doc = vec![
+      GemText::new(GemTag::HeadingOne, "messages")];
+
+    if self.entries.is_empty() {
+      doc.push(GemText::new(GemTag::Text, "no messages yet"));
+      return doc
+    }
+
+    let mut last_day = String::new();
+
+    for entry in self.entries.iter().rev() {
+
+      let day = day(entry.time);
+      if day != last_day {
+        doc.push(GemText::new(GemTag::HeadingTwo, &day));
+        last_day = day;
+      }
+      let line = format!("{} {}", clock(entry.time), entry.text);
+      doc.push(GemText::new(GemTag::ListItem, &line));
+    }
+    doc
+  }
+}
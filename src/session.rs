@@ -0,0 +1,163 @@
+// src/session.rs
+
+use crate::{
+  tab::{Session},
+  pos::{Pos, PosCol},
+};
+use std::{
+  fs::{self, File},
+  io::{Write},
+  path::{Path},
+};
+use toml::{Table, Value};
+use url::{Url};
+
+// module: session
+//
+// a)   Write the open tabs to '.session'
+//      on the way out.
+//
+// b)   Read them back in on the way in.
+//
+// (a) Vec<Session> -> file, (b) file -> Vec<Session>.
+
+
+pub const SESSION: &str = ".session";
+
+
+fn col_to_value(col: &PosCol) -> Value {
+  Value::Array(vec![
+    Value::Integer(col.cursor.into()),
+    Value::Integer(col.scroll as i64),
+  ])
+}
+
+
+fn col_from_value(value: &Value) -> Option<PosCol> {
+  let array = value.as_array()?;
+  Some(PosCol {
+    cursor: array.first()?.as_integer()?.try_into().ok()?,
+    scroll: array.get(1)?.as_integer()?.try_into().ok()?,
+  })
+}
+
+
+fn to_value(session: &Session) -> Value {
+  let mut table = Table::new();
+  table.insert("url".into(), Value::String(session.seed.to_string()));
+  table.insert("x".into(),   col_to_value(&session.pos.x));
+  table.insert("y".into(),   col_to_value(&session.pos.y));
+  Value::Table(table)
+}
+
+
+fn from_value(value: &Value) -> Option<Session> {
+  let table = value.as_table()?;
+  Some(Session {
+    seed: Url::parse(table.get("url")?.as_str()?).ok()?,
+    pos:  Pos {
+      x: col_from_value(table.get("x")?)?,
+      y: col_from_value(table.get("y")?)?,
+    },
+  })
+}
+
+
+// write to a temporary file first,
+// so a crash leaves the old session whole
+pub fn save(dir: &Path, idx: usize, tabs: &[Session])
+  -> Result<(), String>
+{
+  let mut table = Table::new();
+  table.insert("idx".into(), Value::Integer(idx as i64));
+  table.insert(
+    "tabs".into(),
+    Value::Array(tabs.iter().map(to_value).collect()));
+
+  let path = dir.join(SESSION);
+  let tmp = dir.join(format!("{}.tmp", SESSION));
+
+  // on disk before it takes the old one's place
+  File::create(&tmp)
+    .and_then(|mut file| {
+      file.write_all(table.to_string().as_bytes())?;
+      file.sync_all()
+    })
+    .and_then(|_| fs::rename(&tmp, &path))
+    .map_err(|e| e.to_string())
+}
+
+
+// focused tab and every tab, None if there's nothing to restore
+pub fn load(dir: &Path) -> Option<(usize, Vec<Session>)> {
+
+  let table = fs::read_to_string(dir.join(SESSION))
+    .ok()?
+    .parse::<Table>()
+    .ok()?;
+
+  let tabs: Vec<Session> = table
+    .get("tabs")?
+    .as_array()?
+    .iter()
+    .filter_map(from_value)
+    .collect();
+
+  let idx = table
+    .get("idx")
+    .and_then(|v| v.as_integer())
+    .and_then(|i| usize::try_from(i).ok())
+    .unwrap_or(0);
+
+  match tabs.len() {
+    0 => None,
+    n => Some((idx.min(n - 1), tabs)),
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{env};
+
+  #[test]
+  fn save_then_load() {
+    let dir = env::temp_dir()
+      .join(format!("gem-session-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let tabs = vec![
+      Session {
+        seed: Url::parse("gemini://example.org/").unwrap(),
+        pos:  Pos::default(),
+      },
+      Session {
+        seed: Url::parse("gopher://example.org/1/docs").unwrap(),
+        pos:  Pos {
+          x: PosCol {cursor: 2, scroll: 0},
+          y: PosCol {cursor: 7, scroll: 40},
+        },
+      },
+    ];
+    save(&dir, 1, &tabs).unwrap();
+    let loaded = load(&dir);
+    let _ = fs::remove_dir_all(&dir);
+
+    let (idx, loaded) = loaded.unwrap();
+    assert_eq!(idx, 1);
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].seed, tabs[0].seed);
+    assert_eq!(loaded[1].seed, tabs[1].seed);
+    assert_eq!(loaded[1].pos.x.cursor, 2);
+    assert_eq!(loaded[1].pos.y.cursor, 7);
+    assert_eq!(loaded[1].pos.y.scroll, 40);
+  }
+
+  #[test]
+  fn nothing_to_load() {
+    let dir = env::temp_dir()
+      .join(format!("gem-no-session-{}", std::process::id()));
+    assert!(load(&dir).is_none());
+  }
+}
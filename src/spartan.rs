@@ -0,0 +1,115 @@
+// src/spartan.rs
+
+use crate::{
+  gem::{GemDoc, StatusText},
+  util::{connect, read_body, split_whitespace_once, Cancel, FetchError},
+};
+use percent_encoding::{percent_decode};
+use std::{
+  io::{Write},
+};
+use url::{Url};
+
+// module: spartan
+//
+// a)   Send 'host path length' and the data
+//      over plain tcp on port 300.
+//
+// b)   Read spartan's single digit status
+//      codes as their gemini cousins.
+//
+// (a) url + query -> bytes, (b) 2/3/4/5 -> StatusText.
+
+
+// data to upload travels in the query,
+// same as a gemini input response
+pub fn data(url: &Url) -> Vec<u8> {
+  url.query()
+    .map(|query| percent_decode(query.as_bytes()).collect())
+    .unwrap_or_default()
+}
+
+
+pub fn get_data(url: &Url, cancel: &Cancel)
+  -> Result<(String, Vec<u8>), FetchError>
+{
+  let host = url.host_str().unwrap_or("");
+  let urlf = format!("{}:{}", host, url.port().unwrap_or(300));
+
+  let path = match url.path() {
+    "" => "/",
+    p  => p,
+  };
+  let data = data(url);
+
+  let mut stream = connect(&urlf)?;
+  cancel.watch(&stream)?;
+
+  stream.write_all(
+    format!("{} {} {}\r\n", host, path, data.len()).as_bytes())
+    .map_err(|e| e.to_string())?;
+  stream.write_all(&data)
+    .map_err(|e| e.to_string())?;
+
+  let mut response = read_body(&mut stream, cancel)?;
+
+  // separate response from content
+  let clrf = b"\r\n";
+  let content = response
+    .windows(clrf.len())
+    .position(|window| window == clrf)
+    .map(|idx| response.split_off(idx + 2))
+    .unwrap_or_default();
+
+  let response = String::from_utf8_lossy(&response)
+    .to_string();
+
+  Ok((response, content))
+}
+
+
+// spartan status -> gemini status
+pub fn status(response: &str) -> StatusText {
+
+  let (code, meta) = split_whitespace_once(response.trim());
+  let meta = if meta == code {""} else {meta};
+
+  let code = match code {
+    "2" => "20",
+    "3" => "30",
+    "4" => "59",
+    "5" => "50",
+    _   => code,
+  };
+  StatusText::parse(&format!("{} {}", code, meta))
+}
+
+
+pub fn get_doc(url: &Url, cancel: &Cancel)
+  -> Result<GemDoc, FetchError>
+{
+  let (response, content) = get_data(url, cancel)?;
+  Ok(GemDoc::from_response(url, status(&response), content))
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::gem::{Status};
+
+  #[test]
+  fn codes_map_to_gemini() {
+    let ok = status("2 text/plain\r\n");
+    assert!(matches!(ok.tag, Status::Success));
+    assert_eq!(ok.mime.map(|m| m.essence()), Some("text/plain".into()));
+
+    let moved = status("3 /elsewhere\r\n");
+    assert!(matches!(moved.tag, Status::RedirectTemporary));
+    assert_eq!(moved.txt, "/elsewhere");
+
+    assert!(matches!(status("4 bad").tag, Status::FailBadRequest));
+    assert!(matches!(status("5 oops").tag, Status::FailPermanent));
+    assert!(matches!(status("5").tag, Status::FailPermanent));
+  }
+}
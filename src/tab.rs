@@ -2,9 +2,21 @@
 
 use crate::{
   usr::{User},
-  util::{Scheme},
+  util::{
+    Scheme, FetchError, Cancel, query_url, file_name, guess_mime,
+    join_if_relative, without_query,
+  },
+  tofu::{HostCert},
+  ident,
+  titan,
+  local,
+  moved,
+  about,
+  history,
+  bookmark::{self, Bookmark},
   gem::{GemDoc, GemTag, Status},
   text::{Doc},
+  pos::{Pos},
   page::{Page},
   msg::{ViewMsg, InputMsg},
   dlg::{Dialog},
@@ -13,14 +25,46 @@ use crossterm::{
   event::{KeyCode}
 };
 use std::{
-  io::{self, Write}
+  fs,
+  io::{self, Write},
+  path::{Path, Component},
+  time::{SystemTime},
+};
+use tokio::{
+  sync::oneshot::{self, error::TryRecvError},
+  task,
 };
 use url::{Url};
 
 
+// redirects followed for one request
+const MAX_HOPS: usize = 5;
+
+// history entries on either side of the current one
+// that keep their documents
+const MAX_CACHED: usize = 10;
+
+
+// what a tab needs to come back after a restart
 pub struct Session {
   pub seed: Url,
-  pub msgs: Vec<Dialog>,
+  pub pos:  Pos,
+}
+
+
+// a request running on the tokio runtime
+pub struct Fetch {
+  pub url:    Url,
+  pub rx:     oneshot::Receiver<Result<GemDoc, FetchError>>,
+  pub cancel: Cancel,
+  // shown until the response arrives
+  pub ddoc:   Doc,
+  // history entry being revisited, if any
+  pub hist:   Option<usize>,
+  // bytes received when last drawn
+  pub seen:   usize,
+  // query came from a sensitive prompt
+  pub secret: bool,
 }
 
 
@@ -30,36 +74,354 @@ pub struct Tab {
   pub dlg:   Option<(ViewMsg, Dialog)>,
   pub gdoc:  Option<GemDoc>,
   pub ddoc:  Doc,
+  pub fetch: Option<Fetch>,
+  // every document visited in this tab, 
+  // where we were in it, and the document itself 
+  // once we've moved on from it
+  pub hist:  Vec<(Url, Pos, Option<GemDoc>)>,
+  pub hidx:  usize,
+  // local file on display, as of when it was read
+  pub mtime: Option<SystemTime>,
+  // urls that redirected on the way to the current request
+  pub hops:  Vec<Url>,
+  // messages for the app's log, taken after every update and poll
+  pub log:   Vec<String>,
 } 
 impl Tab {
 
-  pub fn init(page: &Page, url_str: &str, usr: &User) 
-    -> Self 
-  {
-    let mut tab = Self {
+  // nothing shown, nothing loading
+  pub fn empty(page: &Page, name: &str) -> Self {
+    Self {
       dlg:    None,
       gdoc:   None,
       ddoc:   Doc::default(), 
       page:   page.clone(),
-      name:   url_str.into(),
-    };
+      name:   name.into(),
+      fetch:  None,
+      hist:   vec![],
+      hidx:   0,
+      mtime:  None,
+      hops:   vec![],
+      log:    vec![],
+    }
+  }
+
+
+  pub fn init(page: &Page, url_str: &str, usr: &User) 
+    -> Self 
+  {
+    let mut tab = Self::empty(page, url_str);
     tab.make_request(usr, url_str);
     tab
   }
 
 
+  // start over at the session's url and position
+  pub fn restore(page: &Page, session: Session, usr: &User) 
+    -> Self 
+  {
+    let mut tab = Self::empty(page, session.seed.as_str());
+    tab.hist = vec![(session.seed.clone(), session.pos, None)];
+    tab.request(usr, session.seed, Some(0));
+    tab
+  }
+
+
+  // where this tab is, None if it never got anywhere
+  pub fn session(&self) -> Option<Session> {
+    match (self.hist.get(self.hidx), &self.fetch) {
+      // what was typed in a sensitive prompt isn't kept
+      (_, Some(f)) if f.hist.is_none() && f.secret => 
+        Some(Session {seed: without_query(&f.url), pos: Pos::default()}),
+      (_, Some(f)) if f.hist.is_none() => 
+        Some(Session {seed: f.url.clone(), pos: Pos::default()}),
+      (Some((url, _, _)), _) => 
+        Some(Session {seed: url.clone(), pos: self.ddoc.pos.clone()}),
+      (None, _) => 
+        None,
+    }
+  }
+
+
   // resize ddoc and dialog
   pub fn resize(&mut self, page: &Page) {
 
     self.page = page.clone();
     self.ddoc.resize(page);
 
+    if let Some(f) = &mut self.fetch {
+      f.ddoc.resize(page);
+    }
+
     if let Some((_, d)) = &mut self.dlg {
       d.resize(page);
     }
   }
 
 
+  pub fn is_loading(&self) -> bool {
+    self.fetch.is_some()
+  }
+
+
+  // record a new document in the history, 
+  // or restore position in a revisited one
+  fn visit(&mut self, url: &Url, hist: Option<usize>) {
+
+    self.name = url.to_string();
+
+    match hist {
+      Some(i) => {
+        self.hidx = i;
+        if let Some((u, pos, doc)) = self.hist.get_mut(i) {
+          self.ddoc.pos = pos.clone();
+          // it may have redirected on the way
+          *u = url.clone();
+          // what's on display lives in gdoc
+          *doc = None;
+        }
+      }
+      None => {
+        self.hist.truncate(self.hidx + 1);
+        self.hist.push((url.clone(), self.ddoc.pos.clone(), None));
+        self.hidx = self.hist.len() - 1;
+      }
+    }
+  }
+
+
+  // put the current document away in its history entry,
+  // along with where we were in it
+  fn leave(&mut self) {
+    let Some(gdoc) = self.gdoc.take() 
+      else {return};
+    if let Some((url, pos, doc)) = self.hist.get_mut(self.hidx) 
+      && *url == gdoc.url 
+    {
+      *pos = self.ddoc.pos.clone();
+      *doc = Some(gdoc);
+    }
+    let hidx = self.hidx;
+    for (i, (_, _, doc)) in self.hist.iter_mut().enumerate() {
+      if i.abs_diff(hidx) > MAX_CACHED {
+        *doc = None;
+      }
+    }
+  }
+
+
+  // the entry on display, or the one on its way
+  fn current(&self) -> usize {
+    self.fetch
+      .as_ref()
+      .and_then(|f| f.hist)
+      .unwrap_or(self.hidx)
+  }
+
+
+  pub fn back(&mut self, usr: &User) -> Option<ViewMsg> {
+    let i = self.current().checked_sub(1)?;
+    self.go_to(usr, i)
+  }
+
+
+  pub fn forward(&mut self, usr: &User) -> Option<ViewMsg> {
+    self.go_to(usr, self.current() + 1)
+  }
+
+
+  // show entry i as we left it,
+  // fetch it only if it never arrived
+  fn go_to(&mut self, usr: &User, i: usize) -> Option<ViewMsg> {
+
+    let Some(gdoc) = self.hist.get_mut(i)?.2.take() 
+      else {return self.revisit(usr, i)};
+
+    // whatever was loading isn't wanted anymore
+    if let Some(f) = self.fetch.take() {
+      f.cancel.cancel();
+    }
+    self.leave();
+    self.hops.clear();
+
+    self.hidx = i;
+    self.name = gdoc.url.to_string();
+    self.mtime = local::modified(&gdoc.url);
+    self.ddoc = usr.get_doc(&gdoc, &self.page);
+    self.ddoc.pos = self.hist[i].1.clone();
+    self.gdoc = Some(gdoc);
+
+    Some(ViewMsg::Redraw)
+  }
+
+
+  // fetch entry i again
+  fn revisit(&mut self, usr: &User, i: usize) -> Option<ViewMsg> {
+    let url = self.hist.get(i)?.0.clone();
+    self.hops.clear();
+    self.request(usr, url, Some(i));
+    Some(ViewMsg::Default)
+  }
+
+
+  // bytes received by the running request
+  pub fn progress(&self) -> Option<usize> {
+    self.fetch
+      .as_ref()
+      .map(|f| f.cancel.received())
+  }
+
+
+  // true if more of the response arrived since last asked
+  pub fn progressed(&mut self) -> bool {
+    let Some(f) = &mut self.fetch 
+      else {return false};
+    let received = f.cancel.received();
+    let changed = received != f.seen;
+    f.seen = received;
+    changed
+  }
+
+
+  // what the tab is up to, for the tab overview
+  pub fn status(&self) -> String {
+    match (&self.fetch, &self.gdoc) {
+      (Some(_), _)       => "loading".into(),
+      (None, Some(gdoc)) => gdoc.status.tag.label(),
+      (None, None)       => "empty".into(),
+    }
+  }
+
+
+  pub fn title(&self) -> String {
+    self.gdoc
+      .as_ref()
+      .map(|gdoc| gdoc.title())
+      .unwrap_or(self.name.clone())
+  }
+
+
+  // how far down the document the cursor is
+  pub fn scroll_pct(&self) -> usize {
+    let last = self.ddoc.lines.len().saturating_sub(1);
+    if last == 0 {
+      return 100
+    }
+    let line = self.ddoc.pos.y.data_idx(&self.page.text.y());
+    (line * 100 / last).min(100)
+  }
+
+
+  // hang up on the request, 
+  // a tab that never had a document goes away
+  pub fn cancel(&mut self) -> Option<ViewMsg> {
+
+    let fetch = self.fetch.take()?;
+    fetch.cancel.cancel();
+
+    Some(self.leave_msg())
+  }
+
+
+  // take the response if it has arrived,
+  // returns true if the tab changed
+  pub fn poll(&mut self, usr: &User) -> bool {
+
+    let Some(fetch) = &mut self.fetch 
+      else {return self.watch(usr)};
+
+    let result = match fetch.rx.try_recv() {
+      Ok(result) => 
+        result,
+      Err(TryRecvError::Empty) => 
+        return false,
+      Err(TryRecvError::Closed) => 
+        Err(FetchError::Msg("request went missing".into())),
+    };
+
+    let secret = fetch.secret;
+    // what was typed in a sensitive prompt is never shown
+    let url = match secret {
+      true => without_query(&fetch.url),
+      false => fetch.url.clone(),
+    };
+    let hist = fetch.hist;
+    let cancel = fetch.cancel.clone();
+    self.fetch = None;
+
+    match result {
+      Ok(mut gemdoc) => {
+        if secret {
+          gemdoc.url = url.clone();
+        }
+        if let Status::RedirectTemporary | Status::RedirectPermanent = 
+          gemdoc.status.tag 
+        {
+          // the move was for the query, not the page itself
+          let permanent = !secret &&
+            matches!(gemdoc.status.tag, Status::RedirectPermanent);
+          self.redirect(
+            usr, &url, &gemdoc.status.txt, permanent, hist);
+          return true
+        }
+        moved::rewrite(&usr.dir, &mut gemdoc.doc);
+        if cancel.truncated() {
+          gemdoc.truncate(cancel.max);
+        }
+        self.mtime = local::modified(&url);
+        log_visit(usr, &gemdoc);
+        let prompt = gemdoc.status.tag.is_prompt();
+        self.some_gem_doc(usr, gemdoc);
+        match (prompt, Scheme::from(&url)) {
+          // a prompt isn't a page to go back to
+          (true, _) => {}
+          // an upload can't be repeated by going back to it
+          (false, Scheme::Titan) => self.name = url.to_string(),
+          (false, _) => self.visit(&url, hist),
+        }
+      }
+
+      Err(FetchError::CertChanged(host, old, new)) => 
+        self.cert_changed(usr, &url, &host, &old, &new),
+
+      Err(FetchError::Msg(e)) => 
+        self.none_gem_doc(usr, &e),
+    }
+    true
+  }
+
+
+  // read the local file on display again if it changed,
+  // returns true if it did
+  fn watch(&mut self, usr: &User) -> bool {
+
+    if self.dlg.is_some() || self.mtime.is_none() {
+      return false
+    }
+    let Some(url) = self.hist.get(self.hidx).map(|h| h.0.clone())
+      else {return false};
+
+    // a file being saved may be missing for a moment
+    match local::modified(&url) {
+      Some(mtime) if Some(mtime) != self.mtime => 
+        self.mtime = Some(mtime),
+      _ => 
+        return false,
+    }
+
+    match local::get_doc(&url) {
+      Ok(gemdoc) => {
+        self.some_gem_doc(usr, gemdoc);
+        // stay where we were
+        self.visit(&url, Some(self.hidx));
+      }
+      Err(e) => 
+        self.none_gem_doc(usr, &e),
+    }
+    true
+  }
+
+
   pub fn update(&mut self, usr: &User, kc: &KeyCode) 
     -> Option<ViewMsg> 
   {
@@ -76,7 +438,7 @@ impl Tab {
 
         Some(InputMsg::No) => {
           self.dlg = None;
-          Some(ViewMsg::Default)
+          Some(self.leave_msg())
         }
 
         Some(InputMsg::Ack) => {
@@ -85,10 +447,10 @@ impl Tab {
           msg
         }
 
-        Some(InputMsg::Text(text)) => {
+        Some(InputMsg::Choice(i)) => {
           let msg = 
-            if let ViewMsg::NewTab = m {
-              Some(ViewMsg::Go(text))
+            if let ViewMsg::Choose(msgs) = m {
+              msgs.get(i).cloned()
             } else {
               Some(m.clone())
             };
@@ -96,9 +458,51 @@ impl Tab {
           msg
         }
 
+        Some(InputMsg::Text(text)) => {
+          let msg = match m {
+            ViewMsg::NewTab => 
+              Some(ViewMsg::Go(text)),
+            ViewMsg::Identify(url) => 
+              Some(ViewMsg::UseIdentity(url.clone(), text)),
+            ViewMsg::Download => 
+              Some(ViewMsg::Save(text)),
+            ViewMsg::UploadTo => 
+              Some(ViewMsg::Titan(text)),
+            ViewMsg::PickFile(url) => 
+              Some(ViewMsg::AttachFile(url.clone(), text)),
+            ViewMsg::Compose(url) => 
+              Some(ViewMsg::Attach(
+                url.clone(), 
+                "text/gemini".into(), 
+                format!("{}\n", text).into_bytes())),
+            ViewMsg::Token(url, mime, data) => 
+              Some(ViewMsg::Upload(
+                url.clone(), mime.clone(), data.clone(), text)),
+            ViewMsg::Bookmark(url, title) => 
+              Some(ViewMsg::AddBookmark(
+                url.clone(), title.clone(), text.trim().into())),
+            ViewMsg::Rename(url) => 
+              Some(ViewMsg::Retitle(url.clone(), text)),
+            ViewMsg::Reply(url) => 
+              Url::parse(url)
+                .map(|url| ViewMsg::Follow(
+                  query_url(&url, &text).into()))
+                .ok(),
+            ViewMsg::SecretReply(url) => 
+              Url::parse(url)
+                .map(|url| ViewMsg::FollowSecret(
+                  query_url(&url, &text).into()))
+                .ok(),
+            _ => 
+              Some(m.clone()),
+          };
+          self.dlg = None;
+          msg
+        }
+
         Some(InputMsg::Cancel) => {
           self.dlg = None;
-          Some(ViewMsg::Default)
+          Some(self.leave_msg())
         }
 
         Some(_) => {
@@ -109,6 +513,9 @@ impl Tab {
           None
       }
     // there is no dialog, process keycode
+    } else if kc == &usr.keys.cancel && self.is_loading() {
+      self.cancel()
+
     } else if kc == &usr.keys.global {
         Some(ViewMsg::Global)
 
@@ -138,6 +545,12 @@ impl Tab {
     } else if kc == &usr.keys.cycle_right {
       Some(ViewMsg::CycleRight)
 
+    } else if kc == &usr.keys.back {
+      self.back(usr)
+
+    } else if kc == &usr.keys.forward {
+      self.forward(usr)
+
     // make a dialog
     } else if kc == &usr.keys.delete_tab {
 
@@ -147,6 +560,31 @@ impl Tab {
       self.dlg = Some((ViewMsg::DeleteMe, dlg));
       Some(ViewMsg::Default)
 
+    } else if kc == &usr.keys.bookmark {
+
+      let url = self.hist.get(self.hidx)?.0.clone();
+      let title = self.gdoc.as_ref()?.title();
+      let dlg = usr.text(
+        &self.page, 
+        &format!("bookmark {} in folder (empty for none): ", title));
+      self.dlg = Some((ViewMsg::Bookmark(url.to_string(), title), dlg));
+      Some(ViewMsg::Default)
+
+    } else if kc == &usr.keys.upload {
+
+      // start from the titan twin of this page, if it has one
+      let init = self.gdoc
+        .as_ref()
+        .and_then(|gdoc| titan::from_gemini(&gdoc.url))
+        .map(|url| url.to_string())
+        .unwrap_or_default();
+      let dlg = usr.edit(
+        &self.page, 
+        "upload to: ", 
+        &init);
+      self.dlg = Some((ViewMsg::UploadTo, dlg));
+      Some(ViewMsg::Default)
+
     } else if kc == &usr.keys.new_tab {
 
       let dlg = usr.text(
@@ -172,25 +610,80 @@ impl Tab {
 
       let dialog_tuple = 
         match gemtype {
-          GemTag::Link(Scheme::Gemini, url) => {
-            let dlg = usr.ask(
+          // the bookmarks page can change its own links
+          GemTag::Link(_, url) if self.is_bookmarks() => {
+            let dlg = usr.choose(
               &self.page, 
-              &format!("go to {}?", url));
-            (ViewMsg::Go(url.into()), dlg)
+              &format!("go to {}?", url),
+              &[(usr.keys.yes,      "yes"),
+                (usr.keys.open_tab, "in new tab"),
+                (usr.keys.delete,   "delete"),
+                (usr.keys.rename,   "rename"),
+                (usr.keys.no,       "no")]);
+            let msgs = vec![
+              ViewMsg::Follow(url.to_string()),
+              ViewMsg::Go(url.to_string()),
+              ViewMsg::Unbookmark(url.to_string()),
+              ViewMsg::Rename(url.to_string()),
+              ViewMsg::Default,
+            ];
+            (ViewMsg::Choose(msgs), dlg)
           }
 
-          GemTag::Link(_, url) => {
-            let dlg = usr.ack(
+          GemTag::Link(
+            Scheme::Gemini | Scheme::Gopher | 
+            Scheme::Finger | Scheme::Spartan | 
+            Scheme::File | Scheme::About, 
+            url) => 
+          {
+            let dlg = usr.choose(
               &self.page, 
-              &format!(
-                "Protocol {} not yet supported", 
-                url));
-            (ViewMsg::Default, dlg)
+              &format!("go to {}?", url),
+              &[(usr.keys.yes,      "yes"),
+                (usr.keys.open_tab, "in new tab"),
+                (usr.keys.no,       "no")]);
+            let msgs = vec![
+              ViewMsg::Follow(url.to_string()),
+              ViewMsg::Go(url.to_string()),
+              ViewMsg::Default,
+            ];
+            (ViewMsg::Choose(msgs), dlg)
           }
 
-          gemtext => {
-            let dlg = usr.ack(
+          GemTag::Link(Scheme::Titan, url) => 
+            self.upload_dlg(usr, url.as_str()),
+
+          // spartan sends whatever is typed to url
+          GemTag::Prompt(url) => {
+            let dlg = usr.text(
               &self.page, 
+              &format!("input for {}:", url));
+            (ViewMsg::Reply(url.to_string()), dlg)
+          }
+
+          GemTag::Link(_, url) => 
+            match usr.handler(url.scheme()) {
+              Some(handler) => {
+                let dlg = usr.ask(
+                  &self.page, 
+                  &format!("open {} with {}?", url, handler.cmd));
+                (ViewMsg::Open(
+                  handler.clone(), url.to_string(), "".into()), 
+                 dlg)
+              }
+              None => {
+                let dlg = self.ack(
+                  usr, 
+                  &format!(
+                    "Protocol {} not yet supported", 
+                    url));
+                (ViewMsg::Default, dlg)
+              }
+            }
+
+          gemtext => {
+            let dlg = self.ack(
+              usr, 
               &format!("you've selected {:?}", gemtext));
 
             (ViewMsg::Default, dlg)
@@ -209,13 +702,17 @@ impl Tab {
   pub fn update_usr(&mut self, _usr: &User) {
   }
 
-  // show dialog if there's a dialog, otherwise show ddoc
+  // show dialog if there's a dialog, 
+  // loading screen if there's a fetch, otherwise show ddoc
   pub fn view(&self, writer: &mut impl Write) 
     -> io::Result<()> 
   {
     if let Some((_, d)) = &self.dlg {
       d.view(writer)?;
 
+    } else if let Some(f) = &self.fetch {
+      f.ddoc.view(&self.page, writer)?;
+
     } else {
       self.ddoc.view(&self.page, writer)?;
     }
@@ -226,37 +723,49 @@ impl Tab {
   // might display dialog
   fn some_gem_doc(&mut self, usr: &User, gemdoc: GemDoc) 
   {
-    self.dlg = match gemdoc.status.tag {
+    let dlg = match gemdoc.status.tag {
 
-      Status::InputExpected |
-      Status::InputExpectedSensitive => {
+      Status::InputExpected => {
 
         let dlg = usr.text(&self.page, &gemdoc.status.txt);
-        Some((ViewMsg::Reply, dlg))
+        Some((ViewMsg::Reply(gemdoc.url.to_string()), dlg))
       }
 
-      Status::RedirectTemporary => {
-
-        let dlg = usr.ask(&self.page, &gemdoc.status.txt);
-
-        let new_url = gemdoc.url
-          .join(&gemdoc.status.txt)
-          .unwrap_or(gemdoc.url.clone());
-
-        Some((ViewMsg::Go(new_url.into()), dlg))
-      }
+      Status::InputExpectedSensitive => {
 
-      Status::RedirectPermanent => {
-        let dlg = usr.ask(&self.page, &gemdoc.status.txt);
-        Some((ViewMsg::Go(gemdoc.status.txt.clone()), dlg))
+        let dlg = usr.secret(&self.page, &gemdoc.status.txt);
+        Some((ViewMsg::SecretReply(gemdoc.url.to_string()), dlg))
       }
 
       Status::CertRequiredClient |
       Status::CertRequiredTransient |
       Status::CertRequiredAuthorized => {
 
-        let dlg = usr.ack(&self.page, &gemdoc.status.txt);
-        Some((ViewMsg::Default, dlg))
+        let names = ident::names(&usr.dir);
+        let text = match names.len() {
+          0 => format!(
+            "{}, name a new identity: ", 
+            gemdoc.status.txt),
+          _ => format!(
+            "{}, pick or name a new identity ({}): ", 
+            gemdoc.status.txt, 
+            names.join(", ")),
+        };
+        let dlg = usr.text(&self.page, &text);
+        Some((ViewMsg::Identify(gemdoc.url.to_string()), dlg))
+      }
+
+      Status::Success if !gemdoc.raw.is_empty() => {
+
+        let prompt = match gemdoc.cut {
+          true  => "truncated, save what arrived as: ",
+          false => "save as: ",
+        };
+        let dlg = usr.edit(
+          &self.page, 
+          prompt, 
+          &file_name(&gemdoc.url));
+        Some((ViewMsg::Download, dlg))
       }
 
       _ => {
@@ -264,38 +773,538 @@ impl Tab {
       }
     };
 
+    // a dialog opened while loading stays open
+    if dlg.is_some() {
+      self.dlg = dlg;
+    }
+
+    // a prompt leaves what's on display alone
+    if gemdoc.status.tag.is_prompt() {
+      return
+    }
+
+    self.leave();
+
     self.ddoc = usr.get_doc(&gemdoc, &self.page);
 
     self.gdoc = Some(gemdoc);
   }
 
 
+  // a tab with nothing to show, or on the way,
+  // has no reason to stay
+  fn leave_msg(&self) -> ViewMsg {
+    match (&self.gdoc, &self.fetch) {
+      (None, None) => ViewMsg::DeleteMe,
+      _            => ViewMsg::Default,
+    }
+  }
+
+
   // display dialog
   fn none_gem_doc(&mut self, usr: &User, msg: &str) {
 
-    self.gdoc = None;
+    let dlg  = self.ack(usr, msg);
+
+    self.dlg = Some((self.leave_msg(), dlg));
+  }
+
+
+  // follow from to target, or ask first, 
+  // unless this has gone on too long
+  fn redirect(&mut self, 
+              usr:       &User, 
+              from:      &Url, 
+              target:    &str, 
+              permanent: bool, 
+              hist:      Option<usize>) 
+  {
+    let to = match join_if_relative(from, target) {
+      Ok(to) => to,
+      Err(e) => return self.none_gem_doc(
+        usr, &format!("redirect to {}: {}", target, e)),
+    };
+    self.hops.push(from.clone());
+
+    if self.hops.contains(&to) {
+      let msg = format!("redirect loop back to {}", to);
+      self.none_gem_doc(usr, &msg);
+      return
+
+    } else if self.hops.len() > MAX_HOPS {
+      let msg = format!(
+        "gave up after {} redirects, next was {}", MAX_HOPS, to);
+      self.none_gem_doc(usr, &msg);
+      return
+    }
+
+    let scheme = Scheme::from(&to);
+
+    self.log.push(format!(
+      "{} {} to {}", 
+      from, 
+      if permanent {"moved"} else {"redirects"}, 
+      to));
+
+    if !scheme.is_fetchable() {
+      match usr.handler(to.scheme()) {
+        Some(handler) => {
+          let dlg = usr.ask(
+            &self.page, 
+            &format!("redirect to {}, open with {}?", to, handler.cmd));
+          let msg = ViewMsg::Open(
+            handler.clone(), to.to_string(), "".into());
+          self.dlg = Some((msg, dlg));
+        }
+        None => {
+          let msg = format!("redirect to {}, no way to open it", to);
+          self.none_gem_doc(usr, &msg);
+        }
+      }
 
-    let dlg  = usr.ack(&self.page, msg);
+    } else if scheme.is_remote() && usr.redirects.follows(from, &to) {
+      self.moved(usr, permanent, &to);
+      self.request(usr, to, hist);
 
-    self.dlg = Some((ViewMsg::DeleteMe, dlg));
+    } else {
+      let dlg = usr.ask(
+        &self.page, 
+        &format!("redirect to {}?", to));
+      let msg = ViewMsg::Redirect(to.to_string(), hist, permanent);
+      self.dlg = Some((msg, dlg));
+    }
+  }
+
+
+  // remember a permanent redirect being followed,
+  // the last hop is where it came from.
+  // failing to remember costs a round trip next time
+  fn moved(&self, usr: &User, permanent: bool, to: &Url) {
+    if permanent 
+      && let Some(from) = self.hops.last() 
+    {
+      let _ = moved::remember(&usr.dir, from, to);
+    }
   }
 
 
-  fn make_request(&mut self, usr: &User, url_str: &str) 
+  // keep going after the user agreed to a redirect,
+  // hist as it was for the request that redirected
+  pub fn follow_redirect(&mut self, 
+                         usr:       &User, 
+                         url_str:   &str, 
+                         hist:      Option<usize>, 
+                         permanent: bool) 
   {
     match Url::parse(url_str) {
+      Ok(url) => {
+        self.moved(usr, permanent, &url);
+        self.request(usr, url, hist);
+      }
+      Err(e) => self.none_gem_doc(usr, &e.to_string()),
+    }
+  }
+
+
+  // display trust dialog
+  fn cert_changed(&mut self, 
+                  usr: &User, 
+                  url: &Url, 
+                  host: &str, 
+                  old: &HostCert,
+                  new: &HostCert) 
+  {
+    let text = format!(
+      "{} changed its certificate: {} (was {})", 
+      host, new.fingerprint, old.fingerprint);
+    self.log.push(text.clone());
+
+    let dlg = usr.choose(
+      &self.page, 
+      &text, 
+      &[(usr.keys.yes,    "accept once"),
+        (usr.keys.always, "accept always"),
+        (usr.keys.no,     "abort")]);
+
+    let msgs = vec![
+      ViewMsg::TrustOnce(
+        url.to_string(), host.into(), new.clone()),
+      ViewMsg::TrustAlways(
+        url.to_string(), host.into(), new.clone()),
+      self.leave_msg(),
+    ];
+
+    self.dlg = Some((ViewMsg::Choose(msgs), dlg));
+  }
+
+
+  // where does the data for a titan upload come from?
+  fn upload_dlg(&self, usr: &User, url: &str) 
+    -> (ViewMsg, Dialog) 
+  {
+    let dlg = usr.choose(
+      &self.page, 
+      &format!("upload to {}?", url),
+      &[(usr.keys.file,    "from a file"),
+        (usr.keys.compose, "compose"),
+        (usr.keys.no,      "no")]);
+    let msgs = vec![
+      ViewMsg::PickFile(url.into()),
+      ViewMsg::Compose(url.into()),
+      ViewMsg::Default,
+    ];
+    (ViewMsg::Choose(msgs), dlg)
+  }
 
-      Ok(url) => match GemDoc::new(&url) {
 
-        Ok(gemdoc) => 
-          self.some_gem_doc(usr, gemdoc),
+  pub fn titan(&mut self, usr: &User, url: &str) {
+    if titan::is_titan(url) {
+      self.dlg = Some(self.upload_dlg(usr, url));
+    } else {
+      self.notify(usr, &format!("uploads go to titan:// urls, not {}", url));
+    }
+  }
+
+
+  pub fn pick_file(&mut self, usr: &User, url: &str) {
+    let dlg = usr.text(&self.page, "file to upload: ");
+    self.dlg = Some((ViewMsg::PickFile(url.into()), dlg));
+  }
+
+
+  pub fn compose(&mut self, usr: &User, url: &str) {
+    let dlg = usr.text(&self.page, "text to upload: ");
+    self.dlg = Some((ViewMsg::Compose(url.into()), dlg));
+  }
+
 
-        Err(e) => 
-          self.none_gem_doc(usr, &e),
+  pub fn attach_file(&mut self, usr: &User, url: &str, file: &str) {
+    match fs::read(file) {
+      Ok(data) => 
+        self.attach(usr, url, guess_mime(Path::new(file)), data),
+      Err(e) => 
+        self.notify(usr, &format!("{}: {}", file, e)),
+    }
+  }
+
+
+  // last thing before sending is the token
+  pub fn attach(&mut self, 
+                usr:  &User, 
+                url:  &str, 
+                mime: &str, 
+                data: Vec<u8>) 
+  {
+    let dlg = usr.secret(
+      &self.page, 
+      &format!("token for {} ({} bytes of {}):", 
+               url, data.len(), mime));
+    self.dlg = Some((
+      ViewMsg::Token(url.into(), mime.into(), data), 
+      dlg));
+  }
+
+
+  fn is_bookmarks(&self) -> bool {
+    self.gdoc
+      .as_ref()
+      .is_some_and(|gdoc| gdoc.url.as_str() == about::BOOKMARKS)
+  }
+
+
+  pub fn add_bookmark(&mut self, 
+                      usr:    &User, 
+                      url:    &str, 
+                      title:  &str, 
+                      folder: &str) 
+  {
+    let mark = Bookmark {
+      url:    url.into(),
+      title:  title.into(),
+      folder: folder.into(),
+    };
+    match bookmark::add(&usr.dir, mark) {
+      Ok(()) => 
+        self.notify(usr, &format!("bookmarked {}", title)),
+      Err(e) => 
+        self.notify(usr, &e),
+    }
+  }
+
+
+  pub fn remove_bookmark(&mut self, usr: &User, url: &str) {
+    match bookmark::remove(&usr.dir, url) {
+      Ok(()) => {
+        self.revisit(usr, self.hidx);
       }
+      Err(e) => 
+        self.notify(usr, &e),
+    }
+  }
+
+
+  pub fn rename_bookmark(&mut self, usr: &User, url: &str) {
+    let title = bookmark::find(&usr.dir, url)
+      .map(|mark| mark.title)
+      .unwrap_or_default();
+    let dlg = usr.edit(&self.page, "rename to: ", &title);
+    self.dlg = Some((ViewMsg::Rename(url.into()), dlg));
+  }
+
+
+  pub fn retitle_bookmark(&mut self, usr: &User, url: &str, title: &str) {
+    match bookmark::rename(&usr.dir, url, title) {
+      Ok(()) => {
+        self.revisit(usr, self.hidx);
+      }
+      Err(e) => 
+        self.notify(usr, &e),
+    }
+  }
+
+
+  // write the raw document into the download directory
+  pub fn save(&mut self, usr: &User, name: &str) {
+
+    let dir = usr.dir.join(&usr.download_dir);
+    let path = dir.join(name);
+
+    // a plain name, landing in dir
+    let inside = path.starts_with(&dir) 
+      && Path::new(name)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    if !inside {
+      let text = format!(
+        "won't save outside {}: {}", dir.display(), name);
+      let dlg = self.ack(usr, &text);
+      self.dlg = Some((ViewMsg::Default, dlg));
+      return
+    }
+
+    let Some(gdoc) = &self.gdoc 
+      else {return};
+
+    let saved = fs::create_dir_all(&dir)
+      .and_then(|_| fs::write(&path, &gdoc.raw));
+
+    let handler = gdoc.status.mime
+      .as_ref()
+      .and_then(|mime| usr.handler(&mime.essence()));
+
+    let size = match gdoc.cut {
+      true  => format!("{} bytes (truncated)", gdoc.raw.len()),
+      false => format!("{} bytes", gdoc.raw.len()),
+    };
+
+    self.dlg = match (saved, handler) {
+      (Ok(()), Some(handler)) => {
+        let dlg = usr.ask(
+          &self.page, 
+          &format!(
+            "saved {} to {}, open with {}?", 
+            size, 
+            path.display(), 
+            handler.cmd));
+        let msg = ViewMsg::Open(
+          handler.clone(), 
+          gdoc.url.to_string(), 
+          path.display().to_string());
+        Some((msg, dlg))
+      }
+
+      (Ok(()), None) => {
+        let dlg = self.ack(
+          usr, 
+          &format!(
+            "saved {} to {}", 
+            size, 
+            path.display()));
+        Some((ViewMsg::Default, dlg))
+      }
+
+      (Err(e), _) => {
+        let dlg = self.ack(
+          usr, 
+          &format!(
+            "could not save {}: {}", 
+            path.display(), 
+            e));
+        Some((ViewMsg::Default, dlg))
+      }
+    };
+  }
+
+
+  // tell the user something
+  // an ack dialog, and a note of it in the log
+  fn ack(&mut self, usr: &User, text: &str) -> Dialog {
+    self.log.push(text.into());
+    usr.ack(&self.page, text)
+  }
+
+
+  pub fn notify(&mut self, usr: &User, msg: &str) {
+    let dlg = self.ack(usr, msg);
+    self.dlg = Some((ViewMsg::Default, dlg));
+  }
+
+
+  // msg if the user says yes
+  pub fn confirm(&mut self, usr: &User, text: &str, msg: ViewMsg) {
+    let dlg = usr.ask(&self.page, text);
+    self.dlg = Some((msg, dlg));
+  }
+
+
+  // make identity if needed, bind it to url, try again
+  pub fn use_identity(&mut self, 
+                      usr: &User, 
+                      url_str: &str, 
+                      name: &str) 
+  {
+    let bound = Url::parse(url_str)
+      .map_err(|e| e.to_string())
+      .and_then(|url| {
+        if !ident::names(&usr.dir).iter().any(|n| n == name) {
+          ident::create(&usr.dir, name)?;
+        }
+        ident::bind(&usr.dir, &url, name)
+      });
+
+    match bound {
+      Ok(()) => 
+        self.make_request(usr, url_str),
+      Err(e) => 
+        self.none_gem_doc(usr, &e),
+    }
+  }
+
+
+  pub fn make_request(&mut self, usr: &User, url_str: &str) 
+  {
+    self.hops.clear();
+
+    match Url::parse(url_str) {
+
+      Ok(url) => 
+        self.request(usr, url, None),
 
       Err(e) => 
         self.none_gem_doc(usr, &e.to_string()),
     }
   }
+
+
+  // like make_request, but the query never shows
+  pub fn make_secret_request(&mut self, usr: &User, url_str: &str) 
+  {
+    self.make_request(usr, url_str);
+
+    if let Some(f) = &mut self.fetch {
+      f.secret = true;
+      f.ddoc = loading(usr, &self.page, &without_query(&f.url));
+    }
+  }
+
+
+  // start fetching url
+  fn request(&mut self, usr: &User, url: Url, hist: Option<usize>) 
+  {
+    // skip what we know moved for good
+    let url = moved::resolve(&usr.dir, &url);
+    let task_url = url.clone();
+
+    self.spawn(usr, url, hist, move |dir, cancel| 
+      GemDoc::new(&task_url, dir, cancel));
+  }
+
+
+  pub fn upload(&mut self, 
+                usr:   &User, 
+                url:   &str, 
+                mime:  &str, 
+                data:  Vec<u8>, 
+                token: &str) 
+  {
+    let url = match Url::parse(url) {
+      Ok(url) if Scheme::from(&url) == Scheme::Titan => url,
+      Ok(url) => return self.none_gem_doc(
+        usr, &format!("uploads go to titan:// urls, not {}", url)),
+      Err(e) => return self.none_gem_doc(usr, &e.to_string()),
+    };
+
+    let task_url = url.clone();
+    let mime = mime.to_string();
+    let token = token.to_string();
+
+    self.spawn(usr, url, None, move |dir, cancel| 
+      titan::upload(&task_url, &mime, &data, &token, dir, cancel));
+  }
+
+
+  // run job on the tokio runtime, Tab::poll picks up the result
+  fn spawn<F>(&mut self, 
+              usr:  &User, 
+              url:  Url, 
+              hist: Option<usize>, 
+              job:  F)
+    where F: FnOnce(&Path, &Cancel) -> Result<GemDoc, FetchError> 
+             + Send + 'static
+  {
+    // a new request replaces the old one
+    if let Some(f) = self.fetch.take() {
+      f.cancel.cancel();
+    }
+
+    let (tx, rx) = oneshot::channel();
+    let cancel = Cancel::new(usr.max_size);
+    let task_cancel = cancel.clone();
+    let dir = usr.dir.clone();
+
+    task::spawn_blocking(move || {
+      // nobody listening means the tab is gone
+      let _ = tx.send(job(&dir, &task_cancel));
+    });
+
+    self.fetch = Some(Fetch {
+      ddoc: loading(usr, &self.page, &url),
+      url, 
+      rx, 
+      cancel,
+      hist,
+      seen: 0,
+      secret: false,
+    });
+  }
+}
+
+
+// pages of our own don't count as visits,
+// and a spartan query is data that was sent, not an address
+fn log_visit(usr: &User, gemdoc: &GemDoc) {
+
+  let url = match Scheme::from(&gemdoc.url) {
+    Scheme::About   => return,
+    Scheme::Spartan => without_query(&gemdoc.url),
+    _               => gemdoc.url.clone(),
+  };
+  if !matches!(gemdoc.status.tag, Status::Success) {
+    return
+  }
+  let title = match gemdoc.title() {
+    t if t == gemdoc.url.as_str() => url.to_string(),
+    t => t,
+  };
+  // failing to remember is not a reason to refuse
+  let _ = history::append(&usr.dir, &url, &title);
+}
+
+
+// shown while url loads
+fn loading(usr: &User, page: &Page, url: &Url) -> Doc {
+  let text = usr.layout.text
+    .get_text(&format!("loading {} ...", url))
+    .wrap();
+  Doc::new(vec![text], page)
 }
@@ -311,6 +311,8 @@ pub struct Editor {
   pub pos:    PosCol,
   pub txt:    String,
   pub color:  Color,
+  // show '*' instead of what's typed
+  pub mask:   bool,
 }
 impl Editor {
 
@@ -321,10 +323,17 @@ impl Editor {
       pos:    page.pos().x,
       color:  color,
       txt:    txt.into(),
+      mask:   false,
     }
   }
 
 
+  pub fn mask(mut self) -> Self {
+    self.mask = true;
+    self
+  }
+
+
   pub fn move_left(&mut self, page: &Page, step: u16) 
     -> bool 
   {
@@ -356,7 +365,11 @@ impl Editor {
       page.text.x();
 
     for x_pos in x_start..x_end {
-      let c = chars.next().unwrap_or(' ');
+      let c = match chars.next() {
+        Some(_) if self.mask => '*',
+        Some(c) => c,
+        None => ' ',
+      };
 
       wrt
         .queue(MoveTo(x_pos, self.pos.cursor))?
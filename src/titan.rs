@@ -0,0 +1,91 @@
+// src/titan.rs
+
+use crate::{
+  gem::{GemDoc, StatusText},
+  util::{send_data, Cancel, FetchError, Scheme},
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::{
+  path::{Path},
+};
+use url::{Url};
+
+// module: titan
+//
+// a)   Put mime, size and token on the end
+//      of a titan url's path.
+//
+// b)   Send the data after it, the same way
+//      gemini requests go out.
+//
+// (a) url -> url;mime=..., (b) bytes -> GemDoc.
+
+
+// gemini pages upload to the same place over titan,
+// None for pages from anywhere else
+pub fn from_gemini(url: &Url) -> Option<Url> {
+  if Scheme::from(url) != Scheme::Gemini {
+    return None
+  }
+  let mut titan = url.clone();
+  titan.set_query(None);
+  titan.set_fragment(None);
+  titan.set_scheme("titan").ok()?;
+  Some(titan)
+}
+
+
+// somewhere an upload can go
+pub fn is_titan(url: &str) -> bool {
+  Url::parse(url)
+    .map(|url| Scheme::from(&url) == Scheme::Titan)
+    .unwrap_or(false)
+}
+
+
+// 'titan://host/path;mime=text/gemini;size=12;token=...'
+pub fn params(url: &Url, mime: &str, size: usize, token: &str)
+  -> Url
+{
+  let mut path = format!("{};mime={};size={}", url.path(), mime, size);
+  if !token.is_empty() {
+    path.push_str(";token=");
+    path.extend(utf8_percent_encode(token, NON_ALPHANUMERIC));
+  }
+  let mut titan = url.clone();
+  titan.set_path(&path);
+  titan
+}
+
+
+pub fn upload(url:    &Url,
+              mime:   &str,
+              data:   &[u8],
+              token:  &str,
+              dir:    &Path,
+              cancel: &Cancel)
+  -> Result<GemDoc, FetchError>
+{
+  let titan = params(url, mime, data.len(), token);
+  let (response, content) = send_data(&titan, data, dir, cancel)?;
+  let status = StatusText::parse(&response);
+  // keep the token out of the tab
+  Ok(GemDoc::from_response(url, status, content))
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn params_go_on_the_path() {
+    let url = Url::parse("titan://example.org/notes.gmi").unwrap();
+    assert_eq!(
+      params(&url, "text/gemini", 12, "").as_str(),
+      "titan://example.org/notes.gmi;mime=text/gemini;size=12");
+    assert_eq!(
+      params(&url, "text/plain", 0, "a b;c").as_str(),
+      "titan://example.org/notes.gmi;mime=text/plain;size=0;token=a%20b%3Bc");
+  }
+}
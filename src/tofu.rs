@@ -0,0 +1,208 @@
+// src/tofu.rs
+
+use crate::{
+  util::{now},
+};
+use std::{
+  collections::HashMap,
+  fs,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+use sha2::{Digest, Sha256};
+use toml::{Table, Value};
+use x509_parser::parse_x509_certificate;
+
+// module: tofu
+//
+// a)   Remember the certificate each host presents
+//      the first time we talk to it.
+//
+// b)   Compare later certificates against that record.
+//
+// (a) write '.known_hosts', (b) refuse surprises.
+
+
+pub const KNOWN_HOSTS: &str = ".known_hosts";
+
+// certificates accepted for this run only
+static SESSION: Mutex<Vec<(String, String)>> =
+  Mutex::new(vec![]);
+
+
+pub fn trust_once(host: &str, cert: &HostCert) {
+  if let Ok(mut session) = SESSION.lock() {
+    session.push((host.into(), cert.fingerprint.clone()));
+  }
+}
+
+
+fn trusted_once(host: &str, cert: &HostCert) -> bool {
+  SESSION.lock()
+    .map(|session| session
+      .iter()
+      .any(|(h, f)| h == host && f == &cert.fingerprint))
+    .unwrap_or(false)
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct HostCert {
+  pub fingerprint: String,
+  pub expires:     i64,
+}
+impl HostCert {
+
+  pub fn from_der(der: &[u8]) -> Result<Self, String> {
+
+    let (_, cert) = parse_x509_certificate(der)
+      .map_err(|e| e.to_string())?;
+
+    let fingerprint = Sha256::digest(der)
+      .iter()
+      .map(|b| format!("{:02x}", b))
+      .collect::<Vec<String>>()
+      .join(":");
+
+    Ok(Self {
+      fingerprint,
+      expires: cert.validity().not_after.timestamp(),
+    })
+  }
+
+
+  pub fn is_expired(&self) -> bool {
+    self.expires < now()
+  }
+
+
+  fn from_value(value: &Value) -> Option<Self> {
+    let table = value.as_table()?;
+    Some(Self {
+      fingerprint: table.get("fingerprint")?.as_str()?.into(),
+      expires:     table.get("expires")?.as_integer()?,
+    })
+  }
+
+
+  fn to_value(&self) -> Value {
+    let mut table = Table::new();
+    table.insert(
+      "fingerprint".into(),
+      Value::String(self.fingerprint.clone()));
+    table.insert(
+      "expires".into(),
+      Value::Integer(self.expires));
+    Value::Table(table)
+  }
+}
+
+
+// the outcome of comparing a certificate
+// against the known hosts
+#[derive(Clone, Debug)]
+pub enum Trust {
+  // never seen this host
+  New,
+  // same certificate as last time
+  Known,
+  // old certificate expired, new one is expected
+  Renewed,
+  // certificate changed while old one was still valid
+  Changed(HostCert),
+}
+
+
+pub struct KnownHosts {
+  pub path:  PathBuf,
+  pub hosts: HashMap<String, HostCert>,
+}
+impl KnownHosts {
+
+  // missing or broken file means no known hosts
+  pub fn load(path: &Path) -> Self {
+
+    let hosts = fs::read_to_string(path)
+      .ok()
+      .and_then(|txt| txt.parse::<Table>().ok())
+      .map(|table| table
+        .iter()
+        .filter_map(|(host, value)|
+          HostCert::from_value(value)
+            .map(|cert| (host.clone(), cert)))
+        .collect())
+      .unwrap_or_default();
+
+    Self {path: path.into(), hosts}
+  }
+
+
+  pub fn save(&self) -> Result<(), String> {
+
+    let table: Table = self.hosts
+      .iter()
+      .map(|(host, cert)| (host.clone(), cert.to_value()))
+      .collect();
+
+    fs::write(&self.path, table.to_string())
+      .map_err(|e| e.to_string())
+  }
+
+
+  pub fn check(&self, host: &str, cert: &HostCert) -> Trust {
+    match self.hosts.get(host) {
+      None =>
+        Trust::New,
+
+      Some(known) if known == cert =>
+        Trust::Known,
+
+      Some(_) if trusted_once(host, cert) =>
+        Trust::Known,
+
+      Some(known) if known.is_expired() =>
+        Trust::Renewed,
+
+      Some(known) =>
+        Trust::Changed(known.clone()),
+    }
+  }
+
+
+  pub fn trust(&mut self, host: &str, cert: &HostCert) {
+    self.hosts.insert(host.into(), cert.clone());
+  }
+}
+
+
+// check cert against the known hosts file at path,
+// remember it if that's the right thing to do.
+pub fn verify(path: &Path, host: &str, cert: &HostCert)
+  -> Result<(), HostCert>
+{
+  let mut known = KnownHosts::load(path);
+
+  match known.check(host, cert) {
+    Trust::Known =>
+      Ok(()),
+
+    Trust::New | Trust::Renewed => {
+      known.trust(host, cert);
+      let _ = known.save();
+      Ok(())
+    }
+
+    Trust::Changed(old) =>
+      Err(old),
+  }
+}
+
+
+// replace the record for host with cert
+pub fn trust_always(path: &Path, host: &str, cert: &HostCert)
+  -> Result<(), String>
+{
+  let mut known = KnownHosts::load(path);
+  known.trust(host, cert);
+  known.save()
+}
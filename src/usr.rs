@@ -4,7 +4,7 @@ use crate::{
   gem::{GemDoc, GemTag, GemText},
   text::{Doc, Text, Editor},
   page::{Rect, Page},
-  util::{parse_color},
+  util::{parse_color, u16_or_max},
   dlg::{Dialog, InputType},
 };
 use crossterm::{
@@ -12,6 +12,10 @@ use crossterm::{
   event::KeyCode,
 };
 use toml::{Table, Value};
+use url::{Url};
+use std::{
+  path::{Path, PathBuf},
+};
 
 // module: usr
 //
@@ -26,8 +30,12 @@ use toml::{Table, Value};
 #[derive(Debug)]
 enum UserKey {
   InitUrl,
+  DownloadDir,
+  MaxSize,
+  Redirects,
   Layout,
   Keys,
+  Handlers,
 }
 impl UserKey {
 
@@ -36,8 +44,12 @@ impl UserKey {
   {
     match key {
       "init_url" => Ok(Self::InitUrl),
+      "download_dir" => Ok(Self::DownloadDir),
+      "max_size" => Ok(Self::MaxSize),
+      "redirects" => Ok(Self::Redirects),
       "layout"   => Ok(Self::Layout),
       "keys"     => Ok(Self::Keys),
+      "handlers" => Ok(Self::Handlers),
       key => 
         Err(
           format!(
@@ -52,6 +64,15 @@ pub struct User {
   pub init_url:  String,
   pub layout:    UserLayout,
   pub keys:      UserKeys,
+  // relative to dir
+  pub download_dir: PathBuf,
+  // bytes to read from a response before giving up
+  pub max_size:  usize,
+  pub redirects: RedirectPolicy,
+  // scheme or mime type, handler
+  pub handlers:  Vec<(String, UserHandler)>,
+  // directory holding '.gemset' and friends
+  pub dir:       PathBuf,
 } 
 impl Default for User {
 
@@ -60,11 +81,26 @@ impl Default for User {
       init_url: "gemini://datapulp.smol.pub/".into(),
       layout:    UserLayout::default(),
       keys:      UserKeys::default(),
+      download_dir: "downloads".into(),
+      max_size:  16 * 1024 * 1024,
+      redirects: RedirectPolicy::Ask,
+      handlers:  vec![],
+      dir:       PathBuf::new(),
     }
   }
 }
 impl User {
 
+  // moves self to the assignee
+  pub fn dir(mut self, path: &str) -> Self {
+    self.dir = Path::new(path)
+      .parent()
+      .map(PathBuf::from)
+      .unwrap_or_default();
+    self
+  }
+
+
   pub fn read_table(mut self, table: &Table) 
     -> Result<Self, String> 
   {
@@ -97,6 +133,35 @@ impl User {
             "init_url key expects a string value".into())
         }
       }
+      UserKey::DownloadDir => {
+
+        if let Value::String(s) = value {
+          self.download_dir = s.into();
+
+        } else {
+          return Err(
+            "download_dir key expects a string value".into())
+        }
+      }
+      UserKey::MaxSize => {
+
+        match value.as_integer().map(usize::try_from) {
+          Some(Ok(n)) if n > 0 => self.max_size = n,
+          _ => 
+            return Err(
+              "max_size key expects a positive integer value".into())
+        }
+      }
+      UserKey::Redirects => {
+
+        if let Value::String(s) = value {
+          self.redirects = RedirectPolicy::try_from_string(s)?;
+
+        } else {
+          return Err(
+            "redirects key expects a string value".into())
+        }
+      }
       UserKey::Layout => {
 
         if let Value::Table(t) = value {
@@ -119,11 +184,39 @@ impl User {
             "keys key expects a table value".into())
         }
       }
+      UserKey::Handlers => {
+
+        if let Value::Table(t) = value {
+          self.handlers = UserHandler::read_table(t)?;
+
+        } else {
+          return Err(
+            "handlers key expects a table value".into())
+        }
+      }
     }
     Ok(())
   }
 
 
+  // handler for a scheme like 'https' or a mime type 
+  // like 'image/png', which 'image/*' also matches
+  pub fn handler(&self, key: &str) -> Option<&UserHandler> {
+
+    let wild = key
+      .split_once('/')
+      .map(|(kind, _)| format!("{}/*", kind));
+
+    self.handlers
+      .iter()
+      .find(|(k, _)| k == key)
+      .or_else(|| self.handlers
+        .iter()
+        .find(|(k, _)| Some(k) == wild.as_ref()))
+      .map(|(_, h)| h)
+  }
+
+
   pub fn get_layout(&self, w: u16, h: u16) -> (Page, Page) 
   {
     let rect = Rect::new(w, h);
@@ -163,6 +256,40 @@ impl User {
   }
 
 
+  // text dialog with something already typed
+  pub fn edit(&self, page: &Page, text: &str, init: &str) 
+    -> Dialog 
+  {
+    let mut dlg = Dialog::new(page, text);
+    let mut pos = dlg.input_page.pos();
+    let color = self.layout.dialog.unwrap_or(Color::White);
+    let editor = Editor::new(&dlg.input_page, init, color);
+
+    // cursor rests on the last character
+    pos.x.move_forward(
+      &dlg.input_page.x(), 
+      init.len(), 
+      u16_or_max(init.len().saturating_sub(1)));
+
+    dlg.input_type = InputType::Text(editor, pos);
+    dlg
+  }
+
+
+  // text dialog that hides what's typed
+  pub fn secret(&self, page: &Page, text: &str) -> Dialog {
+
+    let mut dlg = Dialog::new(page, text);
+    let pos = dlg.input_page.pos();
+    let color = self.layout.dialog.unwrap_or(Color::White);
+    let editor = Editor::new(&dlg.input_page, "", color)
+      .mask();
+
+    dlg.input_type = InputType::Text(editor, pos);
+    dlg
+  }
+
+
   pub fn ack(&self, page: &Page, text: &str) -> Dialog {
 
     let mut dlg = Dialog::new(page, text);
@@ -180,6 +307,22 @@ impl User {
   }
 
 
+  pub fn choose(&self, 
+                page: &Page, 
+                text: &str, 
+                choices: &[(KeyCode, &str)]) 
+    -> Dialog 
+  {
+    let mut dlg = Dialog::new(page, text);
+    dlg.input_type = InputType::Choose(
+      choices
+        .iter()
+        .map(|(k, s)| (*k, String::from(*s)))
+        .collect());
+    dlg
+  }
+
+
   pub fn get_doc(&self, gdoc: &GemDoc, page: &Page) -> Doc {
     let text = self.layout.gemtext_to_text(&gdoc.doc);
     Doc::new(text, &page)
@@ -193,6 +336,102 @@ impl User {
 }
 
 
+// an outside program, 
+// '{url}' and '{file}' in cmd are filled in when it's run
+#[derive(Clone, Debug)]
+pub struct UserHandler {
+  pub cmd:        String,
+  // takes over the terminal until it exits
+  pub foreground: bool,
+}
+impl UserHandler {
+
+  // a string runs in the foreground, a table 
+  // looks like {cmd = "...", foreground = false}
+  pub fn try_from_value(value: &Value) -> Result<Self, String> {
+    match value {
+      Value::String(s) => 
+        Ok(Self {cmd: s.into(), foreground: true}),
+
+      Value::Table(t) => {
+        let cmd = t.get("cmd")
+          .and_then(|v| v.as_str())
+          .ok_or("handler table expects a cmd string")?;
+        let foreground = match t.get("foreground") {
+          None => true,
+          Some(Value::Boolean(b)) => *b,
+          Some(v) => return Err(
+            format!("foreground doesn't take {:?}", v)),
+        };
+        Ok(Self {cmd: cmd.into(), foreground})
+      }
+
+      v => 
+        Err(format!("handler doesn't take {:?}", v)),
+    }
+  }
+
+
+  pub fn read_table(table: &Table) 
+    -> Result<Vec<(String, Self)>, String> 
+  {
+    table
+      .iter()
+      .map(|(key, value)| 
+        Self::try_from_value(value)
+          .map(|h| (key.clone(), h))
+          .map_err(|e| format!("{} : {}", key, e)))
+      .collect()
+  }
+
+
+  // program and arguments with placeholders filled in
+  pub fn args(&self, url: &str, file: &str) -> Vec<String> {
+    self.cmd
+      .split_whitespace()
+      .map(|arg| arg
+        .replace("{url}", url)
+        .replace("{file}", file))
+      .collect()
+  }
+}
+
+
+// which redirects are followed without asking
+#[derive(Clone, Debug, PartialEq)]
+pub enum RedirectPolicy {
+  Ask,
+  SameHost,
+  All,
+}
+impl RedirectPolicy {
+
+  pub fn try_from_string(s: &str) -> Result<Self, String> {
+    match s {
+      "ask"       => Ok(Self::Ask),
+      "same_host" => Ok(Self::SameHost),
+      "all"       => Ok(Self::All),
+      s => 
+        Err(
+          format!(
+            "redirects is one of ask, same_host or all, not {}", s)),
+    }
+  }
+
+
+  pub fn follows(&self, from: &Url, to: &Url) -> bool {
+    match self {
+      Self::Ask      => false,
+      Self::SameHost => 
+        from.scheme() == to.scheme() && 
+        from.host_str() == to.host_str() && 
+        from.port() == to.port(),
+      Self::All      => true,
+    }
+  }
+}
+
+
 #[derive(Debug)]
 enum KeysKey {
   Global, 
@@ -205,12 +444,22 @@ enum KeysKey {
   MoveRight,
   CycleLeft, 
   CycleRight, 
+  Back, 
+  Forward, 
   DelTab, 
+  OpenTab, 
   NewTab, 
+  Upload, 
+  Bookmark, 
+  File, 
+  Compose, 
+  Delete, 
+  Rename, 
   Inspect, 
   Ack, 
   Yes, 
   No, 
+  Always, 
   Cancel,
 }
 impl KeysKey {
@@ -229,12 +478,22 @@ impl KeysKey {
       "move_right"  => Ok(Self::MoveRight),
       "cycle_left"  => Ok(Self::CycleLeft),
       "cycle_right" => Ok(Self::CycleRight),
+      "back"        => Ok(Self::Back),
+      "forward"     => Ok(Self::Forward),
       "delete_tab"  => Ok(Self::DelTab),
       "new_tab"     => Ok(Self::NewTab),
+      "open_tab"    => Ok(Self::OpenTab),
+      "upload"      => Ok(Self::Upload),
+      "bookmark"    => Ok(Self::Bookmark),
+      "file"        => Ok(Self::File),
+      "compose"     => Ok(Self::Compose),
+      "delete"      => Ok(Self::Delete),
+      "rename"      => Ok(Self::Rename),
       "inspect"     => Ok(Self::Inspect),
       "ack"         => Ok(Self::Ack),
       "yes"         => Ok(Self::Yes),
       "no"          => Ok(Self::No),
+      "always"      => Ok(Self::Always),
       "cancel"      => Ok(Self::Cancel),
       key => 
         Err(
@@ -258,12 +517,22 @@ pub struct UserKeys {
   pub move_right:  KeyCode,
   pub cycle_left:  KeyCode,
   pub cycle_right: KeyCode,
+  pub back:        KeyCode,
+  pub forward:     KeyCode,
   pub inspect:     KeyCode,
   pub delete_tab:  KeyCode,
   pub new_tab:     KeyCode,
+  pub open_tab:    KeyCode,
+  pub upload:      KeyCode,
+  pub bookmark:    KeyCode,
+  pub file:        KeyCode,
+  pub compose:     KeyCode,
+  pub delete:      KeyCode,
+  pub rename:      KeyCode,
   pub ack:         KeyCode, 
   pub yes:         KeyCode, 
-  pub no:          KeyCode
+  pub no:          KeyCode,
+  pub always:      KeyCode,
 } 
 impl Default for UserKeys {
 
@@ -280,12 +549,22 @@ impl Default for UserKeys {
       move_right:  KeyCode::Right,
       cycle_left:  KeyCode::Char('E'),
       cycle_right: KeyCode::Char('N'),
+      back:        KeyCode::Char('b'),
+      forward:     KeyCode::Char('f'),
       inspect:     KeyCode::Enter,
       delete_tab:  KeyCode::Char('d'),
       new_tab:     KeyCode::Char('n'),
+      open_tab:    KeyCode::Char('t'),
+      upload:      KeyCode::Char('u'),
+      bookmark:    KeyCode::Char('k'),
+      file:        KeyCode::Char('f'),
+      compose:     KeyCode::Char('c'),
+      delete:      KeyCode::Char('d'),
+      rename:      KeyCode::Char('r'),
       ack:         KeyCode::Enter, 
       yes:         KeyCode::Char('y'), 
-      no:          KeyCode::Char('n')
+      no:          KeyCode::Char('n'),
+      always:      KeyCode::Char('a'),
     }
   }
 }
@@ -306,12 +585,22 @@ impl UserKeys {
       KeysKey::MoveRight  => self.move_right = v,
       KeysKey::CycleLeft  => self.cycle_left = v,
       KeysKey::CycleRight => self.cycle_right = v,
+      KeysKey::Back       => self.back = v,
+      KeysKey::Forward    => self.forward = v,
       KeysKey::DelTab     => self.delete_tab = v,
       KeysKey::NewTab     => self.new_tab = v,
+      KeysKey::OpenTab    => self.open_tab = v,
+      KeysKey::Upload     => self.upload = v,
+      KeysKey::Bookmark   => self.bookmark = v,
+      KeysKey::File       => self.file = v,
+      KeysKey::Compose    => self.compose = v,
+      KeysKey::Delete     => self.delete = v,
+      KeysKey::Rename     => self.rename = v,
       KeysKey::Inspect    => self.inspect = v,
       KeysKey::Ack        => self.ack = v,
       KeysKey::Yes        => self.yes = v,
       KeysKey::No         => self.no = v,
+      KeysKey::Always     => self.always = v,
       KeysKey::Cancel     => self.cancel = v,
     }
     Ok(())
@@ -695,7 +984,7 @@ impl UserLayout {
       GemTag::PreFormat => 
         self.preformat.get_text(&gtxt.txt),
 
-      GemTag::Link(_, _) => 
+      GemTag::Link(_, _) | GemTag::Prompt(_) => 
         self.link.get_text(&gtxt.txt).wrap(),
 
       GemTag::BadLink(_) => 
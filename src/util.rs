@@ -1,14 +1,27 @@
 // src/util.rs
 
+use crate::{
+  tofu::{HostCert, verify, KNOWN_HOSTS},
+  ident,
+};
 use std::{
-  time::{Duration}, 
+  time::{Duration, SystemTime, UNIX_EPOCH}, 
   io::{Write, Read},
-  net::{TcpStream, ToSocketAddrs},
+  net::{TcpStream, ToSocketAddrs, Shutdown},
+  path::{Path},
+  sync::{
+    Arc, Mutex, 
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+  },
 };
 use crossterm::{
   style::{Color},
 };
 use url::{Url, ParseError};
+use percent_encoding::{
+  utf8_percent_encode, percent_decode_str, NON_ALPHANUMERIC,
+};
+use encoding_rs::{Encoding};
 use toml::{Value};
 use native_tls::TlsConnector;
 
@@ -162,66 +175,345 @@ pub fn join_if_relative(base: &Url, url_str: &str)
 }
 
 
+// url with query replaced by percent-encoded text
+pub fn query_url(url: &Url, text: &str) -> Url {
+  let mut url = url.clone();
+  let query = utf8_percent_encode(text, NON_ALPHANUMERIC)
+    .to_string();
+  url.set_query(Some(&query));
+  url
+}
+
+
+// url without the query, for showing
+// what was typed in a sensitive prompt
+pub fn without_query(url: &Url) -> Url {
+  let mut url = url.clone();
+  url.set_query(None);
+  url
+}
+
+
+// last segment of the url's path, or 'download',
+// never a path of its own or a hidden file
+pub fn file_name(url: &Url) -> String {
+  url.path_segments()
+    .and_then(|mut segments| segments.next_back())
+    .map(|s| percent_decode_str(s)
+      .decode_utf8_lossy()
+      .replace(['/', '\\'], "")
+      .trim_start_matches('.')
+      .to_string())
+    .filter(|s| !s.is_empty())
+    .unwrap_or(String::from("download"))
+}
+
+
+// mime type of a local file, going by its extension
+pub fn guess_mime(path: &Path) -> &'static str {
+  match path.extension().and_then(|e| e.to_str()) {
+    Some("gmi" | "gemini") => "text/gemini",
+    Some("txt" | "md")     => "text/plain",
+    Some("html" | "htm")   => "text/html",
+    Some("png")            => "image/png",
+    Some("jpg" | "jpeg")   => "image/jpeg",
+    Some("gif")            => "image/gif",
+    _                      => "application/octet-stream",
+  }
+}
+
+
+// decode bytes as charset, utf-8 if there's no charset.
+// returns text and, if charset was unknown, a note saying so
+pub fn decode(bytes: &[u8], charset: Option<&str>) 
+  -> (String, Option<String>) 
+{
+  let label = charset.unwrap_or("utf-8");
+
+  match Encoding::for_label(label.as_bytes()) {
+    Some(encoding) => {
+      let (text, _, _) = encoding.decode(bytes);
+      (text.into(), None)
+    }
+    None => {
+      let note = format!(
+        "unknown charset {}, decoded as utf-8", label);
+      (String::from_utf8_lossy(bytes).into(), Some(note))
+    }
+  }
+}
+
+
+const DAYS: [&str; 7] = [
+  "Sunday", "Monday", "Tuesday", "Wednesday",
+  "Thursday", "Friday", "Saturday",
+];
+
+
+// seconds since 1970
+pub fn now() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs() as i64)
+    .unwrap_or(0)
+}
+
+
+// 'yyyy-mm-dd weekday utc'
+pub fn day(time: i64) -> String {
+
+  let days = time.div_euclid(86400);
+
+  // days since 1970-01-01 to a civil date
+  let z = days + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z - era * 146097;
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let d = doy - (153 * mp + 2) / 5 + 1;
+  let m = if mp < 10 {mp + 3} else {mp - 9};
+  let y = yoe + era * 400 + if m <= 2 {1} else {0};
+
+  // 1970-01-01 was a thursday
+  let weekday = DAYS[(days + 4).rem_euclid(7) as usize];
+
+  format!("{:04}-{:02}-{:02} {} utc", y, m, d, weekday)
+}
+
+
+// 'hh:mm:ss' in utc
+pub fn clock(time: i64) -> String {
+  let secs = time.rem_euclid(86400);
+  format!("{:02}:{:02}:{:02}",
+          secs / 3600,
+          secs % 3600 / 60,
+          secs % 60)
+}
+
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum Scheme {
   Gemini, 
   Gopher, 
+  Finger, 
+  Spartan, 
+  Titan, 
+  File, 
+  About, 
   Http, 
   Unknown
 }
 impl From<&Url> for Scheme {
   fn from(url: &Url) -> Scheme {
     match url.scheme() {
-      "gemini" => Scheme::Gemini,
-      "gopher" => Scheme::Gopher,
-      "http"   => Scheme::Http,
-      "https"  => Scheme::Http,
-      _        => Scheme::Unknown,
+      "gemini"  => Scheme::Gemini,
+      "gopher"  => Scheme::Gopher,
+      "finger"  => Scheme::Finger,
+      "spartan" => Scheme::Spartan,
+      "titan"   => Scheme::Titan,
+      "file"    => Scheme::File,
+      "about"   => Scheme::About,
+      "http"    => Scheme::Http,
+      "https"   => Scheme::Http,
+      _         => Scheme::Unknown,
+    }
+  }
+}
+impl Scheme {
+
+  // GemDoc::new knows how to get it
+  pub fn is_fetchable(&self) -> bool {
+    !matches!(self, Scheme::Http | Scheme::Unknown)
+  }
+
+
+  // fetched over the network without sending 
+  // anything of ours, safe to go to unasked
+  pub fn is_remote(&self) -> bool {
+    matches!(self, 
+      Scheme::Gemini | Scheme::Gopher | Scheme::Finger | Scheme::Spartan)
+  }
+}
+
+
+// try every address host resolves to, 
+// return the first that answers
+pub fn connect(addr: &str) -> Result<TcpStream, String> {
+
+  let addrs = addr.to_socket_addrs()
+    .map_err(|e| e.to_string())?;
+
+  let mut err = format!("{} resolves to no address", addr);
+
+  for socket_addr in addrs {
+    match TcpStream::connect_timeout(
+      &socket_addr, Duration::new(10, 0)) 
+    {
+      Ok(stream) => return Ok(stream),
+      Err(e) => err = format!("{}: {}", socket_addr, e),
+    }
+  }
+  Err(err)
+}
+
+
+// lets the ui hang up on a request 
+// running somewhere else, and see how far it got
+#[derive(Clone)]
+pub struct Cancel {
+  // cancelled?, connection
+  inner: Arc<Mutex<(bool, Option<TcpStream>)>>,
+  // bytes read so far
+  received:  Arc<AtomicUsize>,
+  // stopped reading at max?
+  truncated: Arc<AtomicBool>,
+  pub max:   usize,
+}
+impl Cancel {
+
+  pub fn new(max: usize) -> Self {
+    Self {
+      inner:     Arc::default(),
+      received:  Arc::default(),
+      truncated: Arc::default(),
+      max,
     }
   }
+
+
+  pub fn received(&self) -> usize {
+    self.received.load(Ordering::Relaxed)
+  }
+
+
+  pub fn truncated(&self) -> bool {
+    self.truncated.load(Ordering::Relaxed)
+  }
+
+
+  pub fn cancel(&self) {
+    if let Ok(mut inner) = self.inner.lock() {
+      inner.0 = true;
+      if let Some(stream) = inner.1.take() {
+        let _ = stream.shutdown(Shutdown::Both);
+      }
+    }
+  }
+
+
+  // remember stream so cancel can close it
+  pub fn watch(&self, stream: &TcpStream) -> Result<(), String> {
+    let mut inner = self.inner.lock()
+      .map_err(|e| e.to_string())?;
+    if inner.0 {
+      return Err("cancelled".into())
+    }
+    inner.1 = stream.try_clone().ok();
+    Ok(())
+  }
+}
+
+
+// read until the stream ends or cancel.max bytes arrive,
+// counting as it goes
+pub fn read_body(stream: &mut impl Read, cancel: &Cancel) 
+  -> Result<Vec<u8>, String> 
+{
+  let mut body = vec![];
+  let mut buf = [0; 16 * 1024];
+
+  loop {
+    let n = stream.read(&mut buf)
+      .map_err(|e| e.to_string())?;
+    if n == 0 {
+      return Ok(body)
+    }
+    body.extend_from_slice(&buf[..n]);
+    cancel.received.store(body.len(), Ordering::Relaxed);
+
+    if body.len() > cancel.max {
+      body.truncate(cancel.max);
+      cancel.truncated.store(true, Ordering::Relaxed);
+      return Ok(body)
+    }
+  }
+}
+
+
+#[derive(Clone, Debug)]
+pub enum FetchError {
+  Msg(String),
+  // host, certificate on record, certificate presented
+  CertChanged(String, HostCert, HostCert),
+}
+impl From<String> for FetchError {
+  fn from(msg: String) -> FetchError {
+    FetchError::Msg(msg)
+  }
+}
+
+
+// returns response and raw content
+pub fn get_data(url: &Url, dir: &Path, cancel: &Cancel) 
+  -> Result<(String, Vec<u8>), FetchError> 
+{
+  send_data(url, &[], dir, cancel)
 }
 
 
-// returns response and content
-pub fn get_data(url: &Url) 
-  -> Result<(String, String), String> 
+// same as get_data, body follows the url
+pub fn send_data(url: &Url, body: &[u8], dir: &Path, cancel: &Cancel) 
+  -> Result<(String, Vec<u8>), FetchError> 
 {
   let host = url.host_str().unwrap_or("");
-  let urlf = format!("{}:1965", host);
+  let urlf = format!("{}:{}", host, url.port().unwrap_or(1965));
 
-  // get connector
-  let connector = TlsConnector::builder()
+  // get connector, with client certificate if one is bound
+  let mut builder = TlsConnector::builder();
+  builder
     .danger_accept_invalid_hostnames(true)
-    .danger_accept_invalid_certs(true)
-    .build()
-    .map_err(|e| e.to_string())?;
+    .danger_accept_invalid_certs(true);
 
-  // get socket address iterator
-  let mut addrs_iter = urlf.to_socket_addrs()
-    .map_err(|e| e.to_string())?;
+  if let Some(name) = ident::find(dir, url) {
+    builder.identity(ident::load(dir, &name)?);
+  }
 
-  // get socket address from socket address iterator
-  let Some(socket_addr) = addrs_iter.next() 
-    else {return Err(format!("{}", urlf))};
+  let connector = builder
+    .build()
+    .map_err(|e| e.to_string())?;
 
   // get tcp stream from socket address
-  let tcpstream = 
-    TcpStream::connect_timeout(
-      &socket_addr, Duration::new(10, 0))
+  let tcpstream = connect(&urlf)?;
+  cancel.watch(&tcpstream)?;
+
+  // get stream from tcp stream, 
+  // ipv6 hosts come bracketed
+  let domain = host.trim_start_matches('[').trim_end_matches(']');
+  let mut stream = connector.connect(domain, tcpstream) 
     .map_err(|e| e.to_string())?;
 
-  // get stream from tcp stream
-  let mut stream = connector.connect(&host, tcpstream) 
+  // get certificate from stream
+  let der = stream.peer_certificate()
+    .map_err(|e| e.to_string())?
+    .ok_or(String::from("host sent no certificate"))?
+    .to_der()
     .map_err(|e| e.to_string())?;
+  let cert = HostCert::from_der(&der)?;
+
+  // compare certificate to the one on record
+  verify(&dir.join(KNOWN_HOSTS), &urlf, &cert)
+    .map_err(|old| 
+      FetchError::CertChanged(urlf.clone(), old, cert))?;
 
-  // write url to stream
+  // write url and body to stream
   stream.write_all(format!("{}\r\n", url).as_bytes())
     .map_err(|e| e.to_string())?;
+  stream.write_all(body)
+    .map_err(|e| e.to_string())?;
   
   // read into response vector
-  let mut response = vec![];
-  stream.read_to_end(&mut response)
-    .map_err(|e| e.to_string())?;
+  let mut response = read_body(&mut stream, cancel)?;
 
   // separate response from content
   let clrf = b"\r\n";
@@ -229,9 +521,7 @@ pub fn get_data(url: &Url)
     .windows(clrf.len())
     .position(|window| window == clrf)
     .map(|idx| response.split_off(idx + 2))
-    .map(|content| String::from_utf8_lossy(&content)
-      .to_string())
-    .unwrap_or(String::from("no content"));
+    .unwrap_or_default();
 
   // convert to String
   let response = String::from_utf8_lossy(&response)
@@ -239,3 +529,91 @@ pub fn get_data(url: &Url)
 
   Ok((response, content))
 }
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use native_tls::{Identity, TlsAcceptor};
+  use rcgen::{CertificateParams, KeyPair};
+  use std::{
+    env, fs, thread,
+    net::{TcpListener},
+  };
+
+  #[test]
+  fn decode_charsets() {
+    assert_eq!(decode("héllo".as_bytes(), None), ("héllo".into(), None));
+    assert_eq!(decode(&[0x68, 0xe9], Some("iso-8859-1")).0, "hé");
+    let (text, note) = decode(b"hi", Some("klingon"));
+    assert_eq!(text, "hi");
+    assert!(note.is_some());
+  }
+
+  #[test]
+  fn day_and_clock() {
+    assert_eq!(day(0), "1970-01-01 Thursday utc");
+    assert_eq!(clock(0), "00:00:00");
+    assert_eq!(day(-1), "1969-12-31 Wednesday utc");
+    assert_eq!(clock(-1), "23:59:59");
+    // a leap day
+    assert_eq!(day(951827696), "2000-02-29 Tuesday utc");
+    assert_eq!(clock(951827696), "12:34:56");
+  }
+
+  #[test]
+  fn connect_to_local_listener() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    assert!(connect(&addr.to_string()).is_ok());
+
+    // nobody listening anymore
+    drop(listener);
+    assert!(connect(&addr.to_string()).is_err());
+  }
+
+  #[test]
+  fn get_data_from_local_server() {
+    let key = KeyPair::generate().unwrap();
+    let cert = CertificateParams::new(vec!["localhost".into()])
+      .unwrap()
+      .self_signed(&key)
+      .unwrap();
+    let identity = Identity::from_pkcs8(
+      cert.pem().as_bytes(), 
+      key.serialize_pem().as_bytes())
+      .unwrap();
+    let acceptor = TlsAcceptor::new(identity).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // answer one request, hand back what was asked
+    let server = thread::spawn(move || {
+      let (tcp, _) = listener.accept().unwrap();
+      let mut stream = acceptor.accept(tcp).unwrap();
+      let mut request = vec![];
+      let mut byte = [0];
+      while !request.ends_with(b"\r\n") {
+        stream.read_exact(&mut byte).unwrap();
+        request.push(byte[0]);
+      }
+      stream.write_all(b"20 text/gemini\r\n# hello\n").unwrap();
+      stream.shutdown().unwrap();
+      String::from_utf8(request).unwrap()
+    });
+
+    let dir = env::temp_dir()
+      .join(format!("gem-get-data-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let url = Url::parse(&format!("gemini://localhost:{}/x", port)).unwrap();
+    let result = get_data(&url, &dir, &Cancel::new(1024));
+    let _ = fs::remove_dir_all(&dir);
+
+    let (response, content) = result.unwrap();
+    assert_eq!(response, "20 text/gemini\r\n");
+    assert_eq!(content, b"# hello\n");
+    assert_eq!(server.join().unwrap(), format!("{}\r\n", url));
+  }
+}